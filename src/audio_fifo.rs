@@ -0,0 +1,148 @@
+use libc::c_void;
+
+use ::{CodecContext, ff, FFmpegError, Frame, Rational, Timestamp};
+
+/// Buffers audio samples so frames of any size can be regrouped into the fixed frame size an
+/// encoder such as AAC demands - the returned frames are timed in 1/sample_rate
+pub struct AudioFifo {
+    ptr: *mut ff::AVAudioFifo,
+    channel_layout: u64,
+    channels: usize,
+    frame_size: usize,
+    next_pts: Option<i64>,
+    sample_format: ff::AVSampleFormat,
+    sample_rate: usize,
+}
+
+impl AudioFifo {
+    /// Creates a FIFO returning frames of frame_size samples, or of whatever is buffered if
+    /// frame_size is zero as it is for encoders which accept variable frame sizes
+    pub fn new(channel_layout: u64, sample_format: ff::AVSampleFormat, sample_rate: usize, frame_size: usize) -> Result<Self, FFmpegError> {
+        let channels = unsafe {
+            ff::av_get_channel_layout_nb_channels(channel_layout) as usize
+        };
+        Self::alloc(channel_layout, channels, sample_format, sample_rate, frame_size)
+    }
+
+    /// Creates a FIFO returning frames which suit the given (opened) encoder
+    pub fn for_encoder(codec_context: &CodecContext) -> Result<Self, FFmpegError> {
+        // An encoder may be set up with a channel count and no layout
+        Self::alloc(codec_context.channel_layout(),
+                    codec_context.channels(),
+                    codec_context.sample_format(),
+                    codec_context.sample_rate(),
+                    codec_context.frame_size())
+    }
+
+    fn alloc(channel_layout: u64, channels: usize, sample_format: ff::AVSampleFormat, sample_rate: usize, frame_size: usize) -> Result<Self, FFmpegError> {
+        if 0 == channels {
+            return Err(FFmpegError::InvalidArgument);
+        }
+
+        unsafe {
+            match ff::av_audio_fifo_alloc(sample_format, channels as i32, frame_size.max(1) as i32) {
+                fifo if !fifo.is_null() => Ok(AudioFifo {
+                    ptr: fifo,
+                    channel_layout,
+                    channels,
+                    frame_size,
+                    next_pts: None,
+                    sample_format,
+                    sample_rate,
+                }),
+                _ => Err(FFmpegError::OutOfMemory),
+            }
+        }
+    }
+
+    /// Returns the samples still buffered as a final, possibly short, frame
+    pub fn flush(&mut self) -> Result<Option<Frame>, FFmpegError> {
+        match self.size() {
+            0 => Ok(None),
+            samples => self.read_frame(samples).map(Some),
+        }
+    }
+
+    /// Returns the next frame of frame_size samples, or None if not enough have been pushed yet
+    pub fn pop(&mut self) -> Result<Option<Frame>, FFmpegError> {
+        match self.size() {
+            0 => Ok(None),
+            samples if 0 == self.frame_size => self.read_frame(samples).map(Some),
+            samples if samples < self.frame_size => Ok(None),
+            _ => {
+                let frame_size = self.frame_size;
+                self.read_frame(frame_size).map(Some)
+            }
+        }
+    }
+
+    /// Buffers the samples of a frame - the first frame pushed sets the starting timestamp and
+    /// the returned frames continue on from it
+    pub fn push(&mut self, frame: &Frame) -> Result<(), FFmpegError> {
+        // av_audio_fifo_write trusts the frame to hold as many planes as the FIFO, and the
+        // timestamps assume every frame is at the same rate
+        if frame.sample_format() != self.sample_format
+            || frame.channels() != self.channels
+            || frame.sample_rate() != self.sample_rate {
+            return Err(FFmpegError::InvalidArgument);
+        }
+
+        if self.next_pts.is_none() {
+            self.next_pts = Some(frame.pts().map_or(0, |pts| pts.rescale(self.time_base()).value));
+        }
+
+        unsafe {
+            match ff::av_audio_fifo_write(self.ptr, frame.extended_data as *mut *mut c_void, frame.samples() as i32) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                written if (written as usize) < frame.samples() => Err(FFmpegError::BufferTooSmall),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn read_frame(&mut self, samples: usize) -> Result<Frame, FFmpegError> {
+        let mut frame = Frame::alloc();
+        frame.set_channel_layout(self.channel_layout);
+        frame.set_channels(self.channels);
+        frame.set_sample_format(self.sample_format);
+        frame.set_sample_rate(self.sample_rate);
+        frame.set_samples(samples);
+        frame.get_buffer()?;
+
+        unsafe {
+            match ff::av_audio_fifo_read(self.ptr, frame.extended_data as *mut *mut c_void, samples as i32) {
+                e if 0 > e => return Err(FFmpegError::from(e)),
+                read => frame.set_samples(read as usize),
+            }
+        }
+
+        let pts = self.next_pts.unwrap_or(0);
+        frame.set_time_base(self.time_base());
+        frame.set_pts(Some(Timestamp::new(pts, self.time_base())));
+        self.next_pts = Some(pts + frame.samples() as i64);
+
+        Ok(frame)
+    }
+
+    /// Returns the number of samples buffered
+    pub fn size(&self) -> usize {
+        unsafe {
+            ff::av_audio_fifo_size(self.ptr) as usize
+        }
+    }
+
+    pub fn time_base(&self) -> Rational {
+        Rational::new(1, self.sample_rate as i32)
+    }
+}
+
+impl Drop for AudioFifo {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ff::av_audio_fifo_free(self.ptr);
+        }
+    }
+}
+
+unsafe impl Send for AudioFifo {}
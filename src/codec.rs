@@ -1,11 +1,61 @@
 use std::ffi::CString;
 use std::ops::Deref;
+use std::ptr::null;
 
-use ::{ff, str_from_utf8_cstr_unchecked};
+use ::{CodecDescriptor, ff, Rational, str_from_utf8_cstr_unchecked};
+use options::{list as option_list, OptionIter};
+
+/// Iterates over every codec registered with FFmpeg, both encoders and decoders
+pub fn list() -> CodecIter {
+    CodecIter {
+        ptr: null(),
+        done: false,
+    }
+}
+
+bitflags! {
+    pub struct CodecCapabilities: u32 {
+        const DRAW_HORIZ_BAND = ff::AV_CODEC_CAP_DRAW_HORIZ_BAND;
+        const DR1 = ff::AV_CODEC_CAP_DR1;
+        const TRUNCATED = ff::AV_CODEC_CAP_TRUNCATED;
+        const DELAY = ff::AV_CODEC_CAP_DELAY;
+        const SMALL_LAST_FRAME = ff::AV_CODEC_CAP_SMALL_LAST_FRAME;
+        const SUBFRAMES = ff::AV_CODEC_CAP_SUBFRAMES;
+        const EXPERIMENTAL = ff::AV_CODEC_CAP_EXPERIMENTAL;
+        const CHANNEL_CONF = ff::AV_CODEC_CAP_CHANNEL_CONF;
+        const FRAME_THREADS = ff::AV_CODEC_CAP_FRAME_THREADS;
+        const SLICE_THREADS = ff::AV_CODEC_CAP_SLICE_THREADS;
+        const PARAM_CHANGE = ff::AV_CODEC_CAP_PARAM_CHANGE;
+        const AUTO_THREADS = ff::AV_CODEC_CAP_AUTO_THREADS;
+        const VARIABLE_FRAME_SIZE = ff::AV_CODEC_CAP_VARIABLE_FRAME_SIZE;
+        const AVOID_PROBING = ff::AV_CODEC_CAP_AVOID_PROBING;
+        const INTRA_ONLY = ff::AV_CODEC_CAP_INTRA_ONLY;
+        const LOSSLESS = ff::AV_CODEC_CAP_LOSSLESS;
+    }
+}
 
 pub struct Codec(*const ff::AVCodec);
 
 impl Codec {
+    pub fn find_decoder(codec_id: ff::AVCodecID) -> Option<Self> {
+        unsafe {
+            match ff::avcodec_find_decoder(codec_id) {
+                codec if !codec.is_null() => Some(Codec(codec)),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn find_decoder_by_name(name: &str) -> Option<Self> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            match ff::avcodec_find_decoder_by_name(name.as_ptr()) {
+                codec if !codec.is_null() => Some(Codec(codec)),
+                _ => None,
+            }
+        }
+    }
+
     pub fn find_encoder(codec_id: ff::AVCodecID) -> Option<Self> {
         unsafe {
             match ff::avcodec_find_encoder(codec_id) {
@@ -25,24 +75,100 @@ impl Codec {
         }
     }
 
+    pub fn capabilities(&self) -> CodecCapabilities {
+        unsafe {
+            CodecCapabilities::from_bits_truncate((*self.0).capabilities as u32)
+        }
+    }
+
     pub fn channel_layouts(&self) -> ChannelLayoutIter {
         unsafe {
             ChannelLayoutIter((*self.0).channel_layouts)
         }
     }
 
+    /// Returns the descriptor shared by all codecs with this codec's id
+    pub fn descriptor(&self) -> Option<CodecDescriptor> {
+        CodecDescriptor::find(self.id())
+    }
+
+    pub fn id(&self) -> ff::AVCodecID {
+        unsafe {
+            (*self.0).id
+        }
+    }
+
+    pub fn is_decoder(&self) -> bool {
+        unsafe {
+            0 != ff::av_codec_is_decoder(self.0)
+        }
+    }
+
+    pub fn is_encoder(&self) -> bool {
+        unsafe {
+            0 != ff::av_codec_is_encoder(self.0)
+        }
+    }
+
+    pub fn long_name(&self) -> &str {
+        unsafe {
+            match (*self.0).long_name {
+                long_name if !long_name.is_null() => str_from_utf8_cstr_unchecked(long_name),
+                _ => "",
+            }
+        }
+    }
+
+    pub fn media_type(&self) -> ff::AVMediaType {
+        unsafe {
+            (*self.0).type_
+        }
+    }
+
     pub fn name(&self) -> &str {
 		unsafe {
 			str_from_utf8_cstr_unchecked((*self.0).name)
 		}
     }
 
+    /// Returns the private options of this codec, such as the x264 "preset"
+    pub fn options(&self) -> OptionIter {
+        unsafe {
+            option_list((*self.0).priv_class)
+        }
+    }
+
+    pub fn pixel_formats(&self) -> PixelFormatIter {
+        unsafe {
+            PixelFormatIter((*self.0).pix_fmts)
+        }
+    }
+
+    /// Returns the profiles this codec recognizes, falling back to those of the codec id
+    pub fn profiles(&self) -> ProfileIter {
+        unsafe {
+            match (*self.0).profiles {
+                profiles if profiles.is_null() => match ff::avcodec_descriptor_get((*self.0).id) {
+                    descriptor if !descriptor.is_null() => ProfileIter((*descriptor).profiles),
+                    _ => ProfileIter(null()),
+                },
+                profiles => ProfileIter(profiles),
+            }
+        }
+    }
+
     pub fn sample_formats(&self) -> SampleFormatIter {
         unsafe {
             SampleFormatIter((*self.0).sample_fmts)
         }
     }
 
+    pub fn supported_frame_rates(&self) -> SupportedFrameRateIter {
+        unsafe {
+            SupportedFrameRateIter((*self.0).supported_framerates)
+        }
+    }
+
     pub fn supported_sample_rates(&self) -> SupportedSampleRateIter {
         unsafe {
             SupportedSampleRateIter((*self.0).supported_samplerates)
@@ -68,6 +194,11 @@ impl From<*const ff::AVCodec> for Codec {
     }
 }
 
+// Codecs are static, read-only tables
+unsafe impl Send for Codec {}
+
+unsafe impl Sync for Codec {}
+
 pub struct ChannelLayoutIter(*const u64);
 
 impl Iterator for ChannelLayoutIter {
@@ -90,6 +221,88 @@ impl Iterator for ChannelLayoutIter {
     }
 }
 
+pub struct CodecIter {
+    ptr: *const ff::AVCodec,
+    done: bool,
+}
+
+impl Iterator for CodecIter {
+    type Item = Codec;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.done {
+            return None;
+        }
+
+        unsafe {
+            match ff::av_codec_next(self.ptr) {
+                codec if !codec.is_null() => {
+                    self.ptr = codec;
+                    Some(Codec(codec))
+                }
+                _ => {
+                    self.done = true;
+                    None
+                }
+            }
+        }
+    }
+}
+
+pub struct PixelFormatIter(*const ff::AVPixelFormat);
+
+impl Iterator for PixelFormatIter {
+    type Item = ff::AVPixelFormat;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.0.is_null() {
+            return None;
+        }
+
+        unsafe {
+            match *self.0 {
+                ff::AVPixelFormat::AV_PIX_FMT_NONE => None,
+                pixel_format => {
+                    self.0 = self.0.offset(1);
+                    Some(pixel_format)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Profile {
+    pub id: i32,
+    pub name: &'static str,
+}
+
+pub struct ProfileIter(*const ff::AVProfile);
+
+impl Iterator for ProfileIter {
+    type Item = Profile;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.0.is_null() {
+            return None;
+        }
+
+        unsafe {
+            match (*self.0).profile {
+                ff::FF_PROFILE_UNKNOWN => None,
+                id => {
+                    let name = str_from_utf8_cstr_unchecked((*self.0).name);
+                    self.0 = self.0.offset(1);
+                    Some(Profile {
+                        id,
+                        name,
+                    })
+                }
+            }
+        }
+    }
+}
+
 pub struct SampleFormatIter(*const ff::AVSampleFormat);
 
 impl Iterator for SampleFormatIter {
@@ -112,6 +325,28 @@ impl Iterator for SampleFormatIter {
     }
 }
 
+pub struct SupportedFrameRateIter(*const ff::AVRational);
+
+impl Iterator for SupportedFrameRateIter {
+    type Item = Rational;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.0.is_null() {
+            return None;
+        }
+
+        unsafe {
+            match *self.0 {
+                ff::AVRational { num: 0, den: 0 } => None,
+                frame_rate => {
+                    self.0 = self.0.offset(1);
+                    Some(frame_rate.into())
+                }
+            }
+        }
+    }
+}
+
 pub struct SupportedSampleRateIter(*const i32);
 
 impl Iterator for SupportedSampleRateIter {
@@ -152,4 +387,4 @@ fn test_codec(codec: &Codec) {
     for supported_sample_rate in codec.supported_sample_rates() {
         println!("Supported Sample Rate: {}", supported_sample_rate);
     }
-}*/
\ No newline at end of file
+}*/
@@ -1,11 +1,9 @@
 use std::ops::{Deref, DerefMut};
 use std::ptr::{null, null_mut};
 
-use num::BigRational;
+use libc::{c_void, EAGAIN};
 
-use libc::EAGAIN;
-
-use super::{avrational_from_bigrational, bigrational_from_avrational, Codec, CodecParams, ff, FFmpegError, Frame, Packet};
+use super::{Codec, CodecParams, Dictionary, ff, FFmpegError, Frame, Options, Packet, Rational};
 
 pub struct CodecContext(*mut ff::AVCodecContext);
 
@@ -52,7 +50,7 @@ impl CodecContext {
     pub fn copy_params(&mut self, codec_params: &CodecParams) -> Result<(), FFmpegError> {
         unsafe {
             match ff::avcodec_parameters_to_context(self.0, &**codec_params) {
-                e if 0 > e => Err(FFmpegError::from(e)),
+                e if 0 > e => Err(FFmpegError::from(e).with_operation("avcodec_parameters_to_context")),
                 _ => Ok(()),
             }
         }
@@ -61,7 +59,16 @@ impl CodecContext {
     pub unsafe fn open(&mut self, codec: &Codec) -> Result<(), FFmpegError> {
         match ff::avcodec_open2(self.0, **codec, null_mut()) {
             0 => Ok(()),
-            e => Err(FFmpegError::from(e)),
+            e => Err(FFmpegError::from(e).with_operation("avcodec_open2")),
+        }
+    }
+
+    /// Opens the codec using the given codec options - on return the options hold the entries
+    /// which the codec did not recognize
+    pub unsafe fn open_with_options(&mut self, codec: &Codec, options: &mut Dictionary) -> Result<(), FFmpegError> {
+        match ff::avcodec_open2(self.0, **codec, options.as_mut_ptr()) {
+            0 => Ok(()),
+            e => Err(FFmpegError::from(e).with_operation("avcodec_open2")),
         }
     }
 
@@ -77,6 +84,14 @@ impl CodecContext {
         }
     }
 
+    /// Returns the number of samples per channel an audio encoder needs in each frame, which
+    /// is zero if the encoder accepts any number
+    pub fn frame_size(&self) -> usize {
+        unsafe {
+            (*self.0).frame_size as usize
+        }
+    }
+
     pub fn height(&self) -> usize {
         unsafe {
             (*self.0).height as usize
@@ -89,24 +104,32 @@ impl CodecContext {
         }
     }
 
+    /// Returns the next decoded frame, timed in the time base of the packets sent to the decoder
     pub fn receive_frame(&self) -> Result<Option<Frame>, FFmpegError> {
         let mut frame = Frame::alloc();
         unsafe {
+            frame.set_time_base(match (*self.0).pkt_timebase {
+                ff::AVRational { num: 0, .. } => (*self.0).time_base.into(),
+                time_base => time_base.into(),
+            });
+
             match ff::avcodec_receive_frame(self.0, &mut *frame) {
                 0 => Ok(Some(frame)),
                 e if e == ff::AVERROR_EOF || e == ff::AVERROR(EAGAIN) => Ok(None),
-                e => Err(FFmpegError::from(e)),
+                e => Err(FFmpegError::from(e).with_operation("avcodec_receive_frame")),
             }
         }
     }
 
+    /// Returns the next encoded packet, timed in the time base of the encoder
     pub fn receive_packet(&self) -> Result<Option<Packet>, FFmpegError> {
         let mut packet = Packet::alloc();
+        packet.set_time_base(self.time_base());
         unsafe {
             match ff::avcodec_receive_packet(self.0, &mut *packet) {
                 0 => Ok(Some(packet)),
                 e if e == ff::AVERROR_EOF || e == ff::AVERROR(EAGAIN) => Ok(None),
-                e => Err(FFmpegError::from(e)),
+                e => Err(FFmpegError::from(e).with_operation("avcodec_receive_packet")),
             }
         }
     }
@@ -123,11 +146,24 @@ impl CodecContext {
         }
     }
 
+    /// Sends a frame to the encoder - a frame timed in another time base is rescaled to that of
+    /// the encoder, leaving the frame itself as it is
     pub fn send_frame(&self, frame: &Frame) -> Result<(), FFmpegError> {
+        let time_base = self.time_base();
+        let rescaled = match frame.time_base() {
+            frame_time_base if 0 == frame_time_base.num || 0 == time_base.num || frame_time_base == time_base => None,
+            _ => {
+                let mut frame = frame.clone();
+                frame.rescale_ts(time_base);
+                Some(frame)
+            }
+        };
+        let frame = rescaled.as_ref().unwrap_or(frame);
+
         unsafe {
             match ff::avcodec_send_frame(self.0, &**frame) {
                 0 => Ok(()),
-                e => Err(FFmpegError::from(e)),
+                e => Err(FFmpegError::from(e).with_operation("avcodec_send_frame")),
             }
         }
     }
@@ -136,15 +172,21 @@ impl CodecContext {
         unsafe {
             match ff::avcodec_send_frame(self.0, null()) {
                 0 => Ok(()),
-                e => Err(FFmpegError::from(e)),
+                e => Err(FFmpegError::from(e).with_operation("avcodec_send_frame")),
             }
         }
     }
 
     pub fn send_packet(&self, packet: Packet) -> Result<(), FFmpegError> {
+        let stream_index = packet.stream_index();
         unsafe {
+            // Remember the time base of the packets so the frames decoded from them can be timed
+            if 0 != packet.time_base().num {
+                (*self.0).pkt_timebase = packet.time_base().into();
+            }
+
             match ff::avcodec_send_packet(self.0, &*packet) {
-                e if 0 > e => Err(FFmpegError::from(e)),
+                e if 0 > e => Err(FFmpegError::from(e).with_operation("avcodec_send_packet").with_stream_index(stream_index)),
                 _ => Ok(()),
             }
         }
@@ -198,11 +240,10 @@ impl CodecContext {
         }
     }
 
-    pub fn set_framerate(&mut self, value: &BigRational) -> Result<(), FFmpegError> {
-        let framerate = avrational_from_bigrational(value)?;
-        Ok(unsafe {
-            (*self.0).framerate = framerate;
-        })
+    pub fn set_framerate(&mut self, value: Rational) {
+        unsafe {
+            (*self.0).framerate = value.into();
+        }
     }
 
     pub fn set_gop_size(&mut self, value: usize) {
@@ -235,10 +276,18 @@ impl CodecContext {
         }
     }
 
-    pub fn set_sample_aspect_ratio(&mut self, value: &BigRational) -> Result<(), FFmpegError> {
-        Ok(unsafe {
-            (*self.0).sample_aspect_ratio = try!(avrational_from_bigrational(value));
-        })
+    /// Sets the profile using one of the names listed by Codec::profiles(), ignoring case
+    pub fn set_profile_by_name(&mut self, name: &str) -> Result<(), FFmpegError> {
+        match self.codec().profiles().find(|profile| profile.name.eq_ignore_ascii_case(name)) {
+            Some(profile) => Ok(self.set_profile(profile.id)),
+            None => Err(FFmpegError::OptionNotFound),
+        }
+    }
+
+    pub fn set_sample_aspect_ratio(&mut self, value: Rational) {
+        unsafe {
+            (*self.0).sample_aspect_ratio = value.into();
+        }
     }
 
     pub fn set_sample_format(&mut self, value: ff::AVSampleFormat) {
@@ -265,10 +314,10 @@ impl CodecContext {
         }
     }
 
-    pub fn set_time_base(&mut self, value: &BigRational) -> Result<(), FFmpegError> {
-        Ok(unsafe {
-            (*self.0).time_base = try!(avrational_from_bigrational(value));
-        })
+    pub fn set_time_base(&mut self, value: Rational) {
+        unsafe {
+            (*self.0).time_base = value.into();
+        }
     }
 
     pub fn set_width(&mut self, value: usize) {
@@ -283,9 +332,9 @@ impl CodecContext {
         }
     }
 
-    pub fn time_base(&self) -> Result<BigRational, FFmpegError> {
+    pub fn time_base(&self) -> Rational {
         unsafe {
-            bigrational_from_avrational(&(*self.0).time_base)
+            (*self.0).time_base.into()
         }
     }
 
@@ -314,6 +363,12 @@ impl DerefMut for CodecContext {
     }
 }
 
+unsafe impl Options for CodecContext {
+    fn options_ptr(&self) -> *mut c_void {
+        self.0 as *mut c_void
+    }
+}
+
 impl Drop for CodecContext {
     #[inline]
     fn drop(&mut self) {
@@ -321,4 +376,7 @@ impl Drop for CodecContext {
             ff::avcodec_free_context(&mut self.0);
         }
     }
-}
\ No newline at end of file
+}
+
+// A codec context may move to another thread, but it must only be used by one thread at a time
+unsafe impl Send for CodecContext {}
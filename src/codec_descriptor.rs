@@ -0,0 +1,76 @@
+use ::{ff, str_from_utf8_cstr_unchecked};
+
+bitflags! {
+    pub struct CodecProperties: u32 {
+        const INTRA_ONLY = ff::AV_CODEC_PROP_INTRA_ONLY as u32;
+        const LOSSY = ff::AV_CODEC_PROP_LOSSY as u32;
+        const LOSSLESS = ff::AV_CODEC_PROP_LOSSLESS as u32;
+        const REORDER = ff::AV_CODEC_PROP_REORDER as u32;
+        const BITMAP_SUB = ff::AV_CODEC_PROP_BITMAP_SUB as u32;
+        const TEXT_SUB = ff::AV_CODEC_PROP_TEXT_SUB as u32;
+    }
+}
+
+/// Describes the properties of a codec id, independent of which encoder or decoder implements it
+pub struct CodecDescriptor(*const ff::AVCodecDescriptor);
+
+impl CodecDescriptor {
+    pub fn find(codec_id: ff::AVCodecID) -> Option<Self> {
+        unsafe {
+            match ff::avcodec_descriptor_get(codec_id) {
+                descriptor if !descriptor.is_null() => Some(CodecDescriptor(descriptor)),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn id(&self) -> ff::AVCodecID {
+        unsafe {
+            (*self.0).id
+        }
+    }
+
+    pub fn is_intra_only(&self) -> bool {
+        self.properties().contains(CodecProperties::INTRA_ONLY)
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        self.properties().contains(CodecProperties::LOSSLESS)
+    }
+
+    pub fn is_lossy(&self) -> bool {
+        self.properties().contains(CodecProperties::LOSSY)
+    }
+
+    pub fn long_name(&self) -> &str {
+        unsafe {
+            match (*self.0).long_name {
+                long_name if !long_name.is_null() => str_from_utf8_cstr_unchecked(long_name),
+                _ => "",
+            }
+        }
+    }
+
+    pub fn media_type(&self) -> ff::AVMediaType {
+        unsafe {
+            (*self.0).type_
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        unsafe {
+            str_from_utf8_cstr_unchecked((*self.0).name)
+        }
+    }
+
+    pub fn properties(&self) -> CodecProperties {
+        unsafe {
+            CodecProperties::from_bits_truncate((*self.0).props as u32)
+        }
+    }
+}
+
+// Codec descriptors are static, read-only tables
+unsafe impl Send for CodecDescriptor {}
+
+unsafe impl Sync for CodecDescriptor {}
@@ -75,4 +75,7 @@ impl From<*const ff::AVCodecParameters> for CodecParams {
         }
         codec_params
     }
-}
\ No newline at end of file
+}
+
+// An owned copy of the parameters, so it can be handed to another thread to open a codec there
+unsafe impl Send for CodecParams {}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ptr::{null, null_mut};
+
+use ::{ff, str_from_utf8_cstr_unchecked};
+
+/// A set of string options, such as "movflags" = "+faststart", passed to FFmpeg when opening
+/// things - calls which take options leave behind the entries they did not recognize
+pub struct Dictionary(*mut ff::AVDictionary);
+
+impl Dictionary {
+    pub fn new() -> Self {
+        Dictionary(null_mut())
+    }
+
+    /// Returns the pointer FFmpeg calls which take options update in place
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut *mut ff::AVDictionary {
+        &mut self.0
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let key = CString::new(key).unwrap();
+        unsafe {
+            match ff::av_dict_get(self.0, key.as_ptr(), null(), 0) {
+                entry if !entry.is_null() => Some(str_from_utf8_cstr_unchecked((*entry).value)),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: &str, value: &str) {
+        let key = CString::new(key).unwrap();
+        let value = CString::new(value).unwrap();
+        unsafe {
+            match ff::av_dict_set(&mut self.0, key.as_ptr(), value.as_ptr(), 0) {
+                e if 0 > e => panic!("out of memory"),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    pub fn iter(&self) -> DictionaryIter {
+        DictionaryIter {
+            dict: self.0,
+            entry: null_mut(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe {
+            ff::av_dict_count(self.0) as usize
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let value = self.get(key).map(str::to_owned);
+        if value.is_some() {
+            let key = CString::new(key).unwrap();
+            unsafe {
+                ff::av_dict_set(&mut self.0, key.as_ptr(), null(), 0);
+            }
+        }
+
+        value
+    }
+}
+
+impl Clone for Dictionary {
+    fn clone(&self) -> Self {
+        let mut dict = Dictionary::new();
+        unsafe {
+            match ff::av_dict_copy(&mut dict.0, self.0, 0) {
+                e if 0 > e => panic!("out of memory"),
+                _ => dict,
+            }
+        }
+    }
+}
+
+impl Debug for Dictionary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Dictionary::new()
+    }
+}
+
+impl Drop for Dictionary {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ff::av_dict_free(&mut self.0);
+        }
+    }
+}
+
+impl<K: AsRef<str> + Eq + Hash, V: AsRef<str>> From<HashMap<K, V>> for Dictionary {
+    fn from(map: HashMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for Dictionary {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut dict = Dictionary::new();
+        for (key, value) in iter {
+            dict.insert(key.as_ref(), value.as_ref());
+        }
+
+        dict
+    }
+}
+
+// Lookups only read the entries, so shared references are safe too
+unsafe impl Send for Dictionary {}
+
+unsafe impl Sync for Dictionary {}
+
+impl<'a> IntoIterator for &'a Dictionary {
+    type Item = (&'a str, &'a str);
+    type IntoIter = DictionaryIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct DictionaryIter<'a> {
+    dict: *const ff::AVDictionary,
+    entry: *mut ff::AVDictionaryEntry,
+    _marker: PhantomData<&'a Dictionary>,
+}
+
+impl<'a> Iterator for DictionaryIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        unsafe {
+            // An empty key with AV_DICT_IGNORE_SUFFIX matches every entry
+            self.entry = ff::av_dict_get(self.dict, b"\0".as_ptr() as *const _, self.entry, ff::AV_DICT_IGNORE_SUFFIX);
+            if self.entry.is_null() {
+                None
+            } else {
+                Some((str_from_utf8_cstr_unchecked((*self.entry).key), str_from_utf8_cstr_unchecked((*self.entry).value)))
+            }
+        }
+    }
+}
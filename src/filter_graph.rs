@@ -0,0 +1,267 @@
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::ptr::{null, null_mut};
+
+use libc::EAGAIN;
+
+use ::{ff, FFmpegError, Frame, Rational};
+
+/// A libavfilter graph with named buffer sources which accept frames and named buffer sinks
+/// which return filtered frames
+pub struct FilterGraph {
+    ptr: *mut ff::AVFilterGraph,
+    sinks: Vec<(String, *mut ff::AVFilterContext)>,
+    sources: Vec<(String, *mut ff::AVFilterContext)>,
+}
+
+impl FilterGraph {
+    pub fn alloc() -> Self {
+        unsafe {
+            match ff::avfilter_graph_alloc() {
+                graph if !graph.is_null() => FilterGraph {
+                    ptr: graph,
+                    sinks: vec![],
+                    sources: vec![],
+                },
+                _ => panic!("out of memory"),
+            }
+        }
+    }
+
+    /// Builds a configured graph from a description such as "aresample=48000,volume=0.5", with an
+    /// audio source named "in" and an audio sink named "out"
+    pub fn audio(description: &str, channel_layout: u64, sample_format: ff::AVSampleFormat, sample_rate: usize, time_base: Rational) -> Result<Self, FFmpegError> {
+        let mut graph = Self::alloc();
+        graph.add_audio_source("in", channel_layout, sample_format, sample_rate, time_base)?;
+        graph.add_audio_sink("out")?;
+        graph.parse(description)?;
+        graph.configure()?;
+
+        Ok(graph)
+    }
+
+    /// Builds a configured graph from a description such as "yadif,scale=1280:-2,fps=30", with a
+    /// video source named "in" and a video sink named "out"
+    pub fn video(description: &str, width: usize, height: usize, pixel_format: ff::AVPixelFormat, time_base: Rational, sample_aspect_ratio: Rational) -> Result<Self, FFmpegError> {
+        let mut graph = Self::alloc();
+        graph.add_video_source("in", width, height, pixel_format, time_base, sample_aspect_ratio)?;
+        graph.add_video_sink("out")?;
+        graph.parse(description)?;
+        graph.configure()?;
+
+        Ok(graph)
+    }
+
+    /// Adds an audio source which the description can refer to as [name] - the time base is that
+    /// of the frames sent to it, such as the stream time base for decoded frames
+    pub fn add_audio_source(&mut self, name: &str, channel_layout: u64, sample_format: ff::AVSampleFormat, sample_rate: usize, time_base: Rational) -> Result<(), FFmpegError> {
+        let args = format!("time_base={}/{}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+                           time_base.num,
+                           time_base.den,
+                           sample_rate,
+                           sample_format as i32,
+                           channel_layout);
+        let filter = self.create_filter("abuffer", name, Some(&args))?;
+        self.sources.push((name.to_owned(), filter));
+
+        Ok(())
+    }
+
+    /// Adds an audio sink which the description can refer to as [name]
+    pub fn add_audio_sink(&mut self, name: &str) -> Result<(), FFmpegError> {
+        let filter = self.create_filter("abuffersink", name, None)?;
+        self.sinks.push((name.to_owned(), filter));
+
+        Ok(())
+    }
+
+    /// Adds a video source which the description can refer to as [name]
+    pub fn add_video_source(&mut self, name: &str, width: usize, height: usize, pixel_format: ff::AVPixelFormat, time_base: Rational, sample_aspect_ratio: Rational) -> Result<(), FFmpegError> {
+        let args = format!("video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+                           width,
+                           height,
+                           pixel_format as i32,
+                           time_base.num,
+                           time_base.den,
+                           sample_aspect_ratio.num,
+                           sample_aspect_ratio.den);
+        let filter = self.create_filter("buffer", name, Some(&args))?;
+        self.sources.push((name.to_owned(), filter));
+
+        Ok(())
+    }
+
+    /// Adds a video sink which the description can refer to as [name]
+    pub fn add_video_sink(&mut self, name: &str) -> Result<(), FFmpegError> {
+        let filter = self.create_filter("buffersink", name, None)?;
+        self.sinks.push((name.to_owned(), filter));
+
+        Ok(())
+    }
+
+    /// Checks the links and negotiates formats - call once after parse() and before sending frames
+    pub fn configure(&mut self) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::avfilter_graph_config(self.ptr, null_mut()) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn create_filter(&mut self, filter_name: &str, name: &str, args: Option<&str>) -> Result<*mut ff::AVFilterContext, FFmpegError> {
+        let filter_name = CString::new(filter_name).unwrap();
+        let name = CString::new(name).unwrap();
+        let args = args.map(|args| CString::new(args).unwrap());
+        let mut filter_context = null_mut();
+        unsafe {
+            let filter = ff::avfilter_get_by_name(filter_name.as_ptr());
+            if filter.is_null() {
+                return Err(FFmpegError::FilterNotFound);
+            }
+
+            let args_ptr = args.as_ref().map_or(null(), |args| args.as_ptr());
+            match ff::avfilter_graph_create_filter(&mut filter_context, filter, name.as_ptr(), args_ptr, null_mut(), self.ptr) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(filter_context),
+            }
+        }
+    }
+
+    /// Adds the filters of a description to the graph - labels refer to the sources and sinks by
+    /// name, and unlabelled ends of the chain connect to the first source and sink
+    pub fn parse(&mut self, description: &str) -> Result<(), FFmpegError> {
+        let description = CString::new(description).unwrap();
+        unsafe {
+            // The sources are open outputs the description reads from, and the sinks are open
+            // inputs it writes to
+            let mut inputs = inout_list(&self.sinks);
+            let mut outputs = inout_list(&self.sources);
+            let result = ff::avfilter_graph_parse_ptr(self.ptr, description.as_ptr(), &mut inputs, &mut outputs, null_mut());
+            ff::avfilter_inout_free(&mut inputs);
+            ff::avfilter_inout_free(&mut outputs);
+
+            match result {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    pub fn sink(&mut self, name: &str) -> Option<BufferSink> {
+        self.sinks.iter()
+                  .find(|&&(ref sink_name, _)| sink_name == name)
+                  .map(|&(_, filter)| BufferSink(filter, PhantomData))
+    }
+
+    pub fn source(&mut self, name: &str) -> Option<BufferSource> {
+        self.sources.iter()
+                    .find(|&&(ref source_name, _)| source_name == name)
+                    .map(|&(_, filter)| BufferSource(filter, PhantomData))
+    }
+}
+
+impl Drop for FilterGraph {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ff::avfilter_graph_free(&mut self.ptr);
+        }
+    }
+}
+
+// Sources and sinks borrow the graph mutably, so they cannot be used from another thread while the
+// graph moves
+unsafe impl Send for FilterGraph {}
+
+/// A buffer sink borrowed from a FilterGraph
+pub struct BufferSink<'a>(*mut ff::AVFilterContext, PhantomData<&'a mut FilterGraph>);
+
+impl<'a> BufferSink<'a> {
+    /// Returns the next filtered frame, or None if more frames must be sent first or the
+    /// graph has reached the end of the stream
+    pub fn receive_frame(&mut self) -> Result<Option<Frame>, FFmpegError> {
+        let mut frame = Frame::alloc();
+        frame.set_time_base(self.time_base());
+        unsafe {
+            match ff::av_buffersink_get_frame(self.0, &mut *frame) {
+                0 => Ok(Some(frame)),
+                e if e == ff::AVERROR_EOF || e == ff::AVERROR(EAGAIN) => Ok(None),
+                e => Err(FFmpegError::from(e)),
+            }
+        }
+    }
+
+    pub fn time_base(&self) -> Rational {
+        unsafe {
+            ff::av_buffersink_get_time_base(self.0).into()
+        }
+    }
+}
+
+/// A buffer source borrowed from a FilterGraph
+pub struct BufferSource<'a>(*mut ff::AVFilterContext, PhantomData<&'a mut FilterGraph>);
+
+impl<'a> BufferSource<'a> {
+    /// Sends a frame into the graph - the frame data is referenced, not copied, and a frame timed
+    /// in another time base is rescaled to that of the source
+    pub fn send_frame(&mut self, frame: &Frame) -> Result<(), FFmpegError> {
+        let time_base = self.time_base();
+        let rescaled = match frame.time_base() {
+            frame_time_base if 0 == frame_time_base.num || 0 == time_base.num || frame_time_base == time_base => None,
+            _ => {
+                let mut frame = frame.clone();
+                frame.rescale_ts(time_base);
+                Some(frame)
+            }
+        };
+        let frame = rescaled.as_ref().unwrap_or(frame);
+
+        unsafe {
+            match ff::av_buffersrc_add_frame_flags(self.0, &**frame as *const _ as *mut _, ff::AV_BUFFERSRC_FLAG_KEEP_REF as i32) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Marks the end of the stream, so the sinks can return any frames the graph still holds
+    pub fn send_eof(&mut self) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::av_buffersrc_add_frame_flags(self.0, null_mut(), 0) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Returns the time base the source expects frames in, which is known once the graph has been
+    /// configured
+    pub fn time_base(&self) -> Rational {
+        unsafe {
+            match (*self.0).outputs {
+                outputs if outputs.is_null() || (*outputs).is_null() => Rational::new(0, 1),
+                outputs => (**outputs).time_base.into(),
+            }
+        }
+    }
+}
+
+unsafe fn inout_list(endpoints: &[(String, *mut ff::AVFilterContext)]) -> *mut ff::AVFilterInOut {
+    let mut head = null_mut();
+    for &(ref name, filter) in endpoints.iter().rev() {
+        let inout = ff::avfilter_inout_alloc();
+        if inout.is_null() {
+            panic!("out of memory");
+        }
+
+        let name = CString::new(name.as_str()).unwrap();
+        (*inout).name = ff::av_strdup(name.as_ptr());
+        (*inout).filter_ctx = filter;
+        (*inout).pad_idx = 0;
+        (*inout).next = head;
+        head = inout;
+    }
+
+    head
+}
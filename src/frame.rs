@@ -1,28 +1,41 @@
-use std::cmp::max;
-use std::mem::transmute;
+use std::mem::{align_of, size_of, transmute};
 use std::ops::{Deref, DerefMut};
-use std::slice::from_raw_parts_mut;
+use std::ptr::{copy_nonoverlapping, null_mut};
+use std::slice::{Chunks, ChunksMut, from_raw_parts, from_raw_parts_mut};
 
-use ::{ff, FFmpegError};
+use ::{ContentLightLevel, ff, FFmpegError, FrameSideDataIter, MasteringDisplay, Rational, Timestamp};
+use side_data;
 
-pub struct Frame(*mut ff::AVFrame);
+/// A frame of decoded audio or video - FFmpeg 3.4 frames do not record their time base, so we keep
+/// it alongside, starting out as 0/1 (unknown) until whatever made the frame sets it - until then
+/// the timestamps read as None
+pub struct Frame(*mut ff::AVFrame, Rational);
 
 impl Frame {
     pub fn alloc() -> Self {
         unsafe {
             match ff::av_frame_alloc() {
-                frame if !frame.is_null() => Frame(frame),
+                frame if !frame.is_null() => Frame(frame, Rational::new(0, 1)),
                 _ => panic!("out of memory"),
             }
         }
     }
 
-    pub fn best_effort_timestamp(&self) -> usize {
+    pub fn best_effort_timestamp(&self) -> Option<Timestamp> {
         unsafe {
-            (*self.0).best_effort_timestamp as usize
+            self.timestamp((*self.0).best_effort_timestamp)
         }
     }
 
+    /// True if the buffers the frame holds are big enough for its current geometry and format
+    fn buffers_fit(&self) -> bool {
+        (0..self.plane_count()).all(|index| match self.plane_len(index) {
+            // Palette planes have no rows
+            Some(_) => self.row_len(index).map_or(true, |row_len| row_len <= self.line_size(index)),
+            None => false,
+        })
+    }
+
     pub fn channels(&self) -> usize {
         unsafe {
             (*self.0).channels as usize
@@ -41,25 +54,137 @@ impl Frame {
         }
     }
 
-    pub fn data<T>(&self, index: usize) -> &[T] {
-        self.data_mut(index)
+    pub fn content_light_level(&self) -> Option<ContentLightLevel> {
+        self.side_data(ff::AVFrameSideDataType::AV_FRAME_DATA_CONTENT_LIGHT_LEVEL).and_then(ContentLightLevel::from_bytes)
     }
 
-    pub fn data_mut<T>(&self, index: usize) -> &mut [T] {
-        let len = max(self.samples(), self.height() * self.line_size(index));
+    /// Copies a contiguous image, such as one from an image decoder, into the frame - the width,
+    /// height and pixel format must already be set, and each row of the image is padded to a
+    /// multiple of align bytes
+    pub fn copy_from_buffer(&mut self, buffer: &[u8], align: usize) -> Result<(), FFmpegError> {
+        let size = self.image_buffer_size(align)?;
+        if buffer.len() < size {
+            return Err(FFmpegError::BufferTooSmall);
+        }
 
         unsafe {
-            from_raw_parts_mut(transmute((*self.0).data[index]), len)
+            // The geometry may have grown since the buffers were made, in which case they are
+            // swapped for new ones - making them writable would copy past their end
+            if !(*self.0).data[0].is_null() && !self.buffers_fit() {
+                *self = self.empty_copy()?;
+            }
+
+            if (*self.0).data[0].is_null() {
+                self.get_buffer()?;
+            } else {
+                self.make_writable()?;
+            }
+
+            let mut src_data = [null_mut(); 4];
+            let mut src_line_size = [0; 4];
+            match ff::av_image_fill_arrays(src_data.as_mut_ptr(),
+                                           src_line_size.as_mut_ptr(),
+                                           buffer.as_ptr(),
+                                           self.pixel_format(),
+                                           self.width() as i32,
+                                           self.height() as i32,
+                                           align as i32) {
+                e if 0 > e => return Err(FFmpegError::from(e).with_operation("av_image_fill_arrays")),
+                _ => (),
+            }
+
+            ff::av_image_copy((*self.0).data.as_mut_ptr(),
+                              (*self.0).linesize.as_mut_ptr(),
+                              src_data.as_mut_ptr() as *mut *const u8,
+                              src_line_size.as_ptr(),
+                              self.pixel_format(),
+                              self.width() as i32,
+                              self.height() as i32);
         }
+
+        Ok(())
     }
 
-    pub fn duration(&self) -> Option<usize> {
+    /// Copies the image into a contiguous buffer with no padding between planes, such as tightly
+    /// packed RGBA for a texture upload - each row is padded to a multiple of align bytes, so an
+    /// align of 1 packs the rows tightly too
+    pub fn copy_to_buffer(&self, align: usize) -> Result<Vec<u8>, FFmpegError> {
+        let size = self.image_buffer_size(align)?;
+        let mut buffer = vec![0; size];
         unsafe {
-            let duration = (*self.0).pkt_duration;
-            if 0 < duration {
-                Some(duration as usize)
-            } else {
-                None
+            // The copy reads as much as the geometry says, which may have outgrown the buffers
+            if (*self.0).data[0].is_null() || !self.buffers_fit() {
+                return Err(FFmpegError::InvalidArgument);
+            }
+
+            match ff::av_image_copy_to_buffer(buffer.as_mut_ptr(),
+                                              size as i32,
+                                              (*self.0).data.as_ptr() as *const *const u8,
+                                              (*self.0).linesize.as_ptr(),
+                                              self.pixel_format(),
+                                              self.width() as i32,
+                                              self.height() as i32,
+                                              align as i32) {
+                e if 0 > e => Err(FFmpegError::from(e).with_operation("av_image_copy_to_buffer")),
+                _ => Ok(buffer),
+            }
+        }
+    }
+
+    /// Makes a frame with its own copy of the data, rather than another reference to the same
+    /// buffers as clone() does
+    pub fn deep_copy(&self) -> Result<Self, FFmpegError> {
+        let mut frame = self.empty_copy()?;
+        unsafe {
+            // An empty frame has no data to copy
+            if (*self.0).data[0].is_null() {
+                return Ok(frame);
+            }
+
+            // av_frame_copy reads as much as the geometry says, which may have outgrown the buffers
+            if !self.buffers_fit() {
+                return Err(FFmpegError::InvalidArgument);
+            }
+
+            frame.get_buffer()?;
+            match ff::av_frame_copy(frame.0, self.0) {
+                e if 0 > e => Err(FFmpegError::from(e).with_operation("av_frame_copy")),
+                _ => Ok(frame),
+            }
+        }
+    }
+
+    /// Returns the rotation in degrees counterclockwise the frame should be displayed at
+    pub fn display_rotation(&self) -> Option<f64> {
+        self.side_data(ff::AVFrameSideDataType::AV_FRAME_DATA_DISPLAYMATRIX).and_then(side_data::display_rotation)
+    }
+
+    /// Returns the duration of the packet the frame was decoded from, or None if it is unknown
+    pub fn duration(&self) -> Option<Timestamp> {
+        unsafe {
+            match (*self.0).pkt_duration {
+                duration if 0 < duration => self.timestamp(duration),
+                _ => None,
+            }
+        }
+    }
+
+    /// Makes a frame with the same format, geometry and properties but no data
+    fn empty_copy(&self) -> Result<Self, FFmpegError> {
+        let mut frame = Self::alloc();
+        frame.1 = self.1;
+        unsafe {
+            let (dst, src) = (&mut *frame.0, &*self.0);
+            dst.format = src.format;
+            dst.width = src.width;
+            dst.height = src.height;
+            dst.channels = src.channels;
+            dst.channel_layout = src.channel_layout;
+            dst.nb_samples = src.nb_samples;
+
+            match ff::av_frame_copy_props(frame.0, self.0) {
+                e if 0 > e => Err(FFmpegError::from(e).with_operation("av_frame_copy_props")),
+                _ => Ok(frame),
             }
         }
     }
@@ -79,6 +204,29 @@ impl Frame {
         }
     }
 
+    /// True if this is an audio frame whose samples are Ts
+    fn holds<T: Sample>(&self) -> bool {
+        let (packed, planar) = T::sample_formats();
+        self.is_audio() && (packed == self.sample_format() || planar == self.sample_format())
+    }
+
+    /// Returns the size of a contiguous buffer holding the image with rows padded to align bytes
+    fn image_buffer_size(&self, align: usize) -> Result<usize, FFmpegError> {
+        unsafe {
+            match ff::av_image_get_buffer_size(self.pixel_format(), self.width() as i32, self.height() as i32, align as i32) {
+                e if 0 > e => Err(FFmpegError::from(e).with_operation("av_image_get_buffer_size")),
+                size => Ok(size as usize),
+            }
+        }
+    }
+
+    /// True for audio frames, which hold samples rather than pixels
+    fn is_audio(&self) -> bool {
+        unsafe {
+            0 < (*self.0).nb_samples
+        }
+    }
+
     pub fn line_size(&self, index: usize) -> usize {
         unsafe {
             (*self.0).linesize[index] as usize
@@ -94,19 +242,208 @@ impl Frame {
         }
     }
 
+    pub fn mastering_display(&self) -> Option<MasteringDisplay> {
+        self.side_data(ff::AVFrameSideDataType::AV_FRAME_DATA_MASTERING_DISPLAY_METADATA).and_then(MasteringDisplay::from_bytes)
+    }
+
     pub fn pixel_format(&self) -> ff::AVPixelFormat {
         unsafe {
             transmute((*self.0).format)
         }
     }
 
-    pub fn pts(&self) -> Option<usize> {
+    /// Returns the bytes of a plane: one per channel of planar audio, or the luma, chroma and
+    /// alpha planes of planar video - None if there is no such plane or its rows run bottom up
+    pub fn plane(&self, index: usize) -> Option<&[u8]> {
+        let len = self.plane_len(index)?;
         unsafe {
-            let pts = (*self.0).pts;
-            if 0 > pts {
-                None
+            Some(from_raw_parts(*(*self.0).extended_data.offset(index as isize), len))
+        }
+    }
+
+    /// Returns the bytes of a plane for writing - None as for plane(), or if the buffer is shared
+    /// with a clone, in which case call make_writable() first
+    pub fn plane_mut(&mut self, index: usize) -> Option<&mut [u8]> {
+        let len = self.plane_len(index)?;
+        unsafe {
+            match ff::av_frame_is_writable(self.0) {
+                0 => None,
+                _ => Some(from_raw_parts_mut(*(*self.0).extended_data.offset(index as isize), len)),
+            }
+        }
+    }
+
+    pub fn plane_count(&self) -> usize {
+        unsafe {
+            if self.is_audio() {
+                match ff::av_sample_fmt_is_planar(self.sample_format()) {
+                    0 => 1,
+                    _ => self.channels(),
+                }
+            } else {
+                match ff::av_pix_fmt_count_planes(self.pixel_format()) {
+                    e if 0 > e => 0,
+                    planes => planes as usize,
+                }
+            }
+        }
+    }
+
+    /// Returns the length in bytes of a plane, sized by the sample format and channels or by the
+    /// pixel format, whose chroma planes may have fewer rows than the luma plane - None if that
+    /// would reach past the end of the buffer holding the plane
+    fn plane_len(&self, index: usize) -> Option<usize> {
+        if index >= self.plane_count() {
+            return None;
+        }
+
+        unsafe {
+            let frame = &*self.0;
+            if frame.extended_data.is_null() || (*frame.extended_data.offset(index as isize)).is_null() {
+                return None;
+            }
+
+            let len = if self.is_audio() {
+                let bytes_per_sample = ff::av_get_bytes_per_sample(self.sample_format()) as usize;
+                let channels = if 1 == self.plane_count() { self.channels() } else { 1 };
+                self.samples() * channels * bytes_per_sample
             } else {
-                Some(pts as usize)
+                let descriptor = ff::av_pix_fmt_desc_get(self.pixel_format());
+                if descriptor.is_null() || 0 > frame.linesize[index] {
+                    return None;
+                }
+
+                // Rounds up, as AV_CEIL_RSHIFT does, so odd sizes keep their last chroma row
+                let height = match index {
+                    1 | 2 => -((-frame.height) >> (*descriptor).log2_chroma_h),
+                    _ => frame.height,
+                };
+
+                frame.linesize[index] as usize * height as usize
+            };
+
+            // The geometry can be changed after the buffers are allocated
+            let buffer = ff::av_frame_get_plane_buffer(self.0, index as i32);
+            if buffer.is_null() {
+                return None;
+            }
+
+            let start = (*buffer).data as usize;
+            let data = *frame.extended_data.offset(index as isize) as usize;
+            match start + (*buffer).size as usize {
+                end if data < start || end < data + len => None,
+                _ => Some(len),
+            }
+        }
+    }
+
+    /// Returns the samples of a plane as T, which must match the sample format - for example f32
+    /// for AV_SAMPLE_FMT_FLT or AV_SAMPLE_FMT_FLTP
+    pub fn plane_samples<T: Sample>(&self, index: usize) -> Option<&[T]> {
+        if !self.holds::<T>() {
+            return None;
+        }
+
+        let plane = self.plane(index)?;
+        if 0 != plane.as_ptr() as usize % align_of::<T>() {
+            return None;
+        }
+
+        unsafe {
+            Some(from_raw_parts(plane.as_ptr() as *const T, plane.len() / size_of::<T>()))
+        }
+    }
+
+    /// Returns the samples of a plane as T for writing - None as for plane_samples() and
+    /// plane_mut()
+    pub fn plane_samples_mut<T: Sample>(&mut self, index: usize) -> Option<&mut [T]> {
+        if !self.holds::<T>() {
+            return None;
+        }
+
+        let plane = self.plane_mut(index)?;
+        if 0 != plane.as_ptr() as usize % align_of::<T>() {
+            return None;
+        }
+
+        unsafe {
+            Some(from_raw_parts_mut(plane.as_mut_ptr() as *mut T, plane.len() / size_of::<T>()))
+        }
+    }
+
+    pub fn pts(&self) -> Option<Timestamp> {
+        unsafe {
+            self.timestamp((*self.0).pts)
+        }
+    }
+
+    pub fn remove_side_data(&mut self, ty: ff::AVFrameSideDataType) {
+        unsafe {
+            ff::av_frame_remove_side_data(self.0, ty);
+        }
+    }
+
+    /// Converts the timestamps to another time base and takes it on - a frame without a time base
+    /// just takes on the new one
+    pub fn rescale_ts(&mut self, time_base: Rational) {
+        let from = self.1;
+        self.1 = time_base;
+        if 0 == from.num {
+            return;
+        }
+
+        let rescale = |value: i64| match value {
+            ff::AV_NOPTS_VALUE => value,
+            value => from.rescale(value, time_base),
+        };
+        unsafe {
+            let frame = &mut *self.0;
+            frame.pts = rescale(frame.pts);
+            frame.pkt_dts = rescale(frame.pkt_dts);
+            frame.best_effort_timestamp = rescale(frame.best_effort_timestamp);
+            frame.pkt_duration = rescale(frame.pkt_duration);
+        }
+    }
+
+    /// Iterates over the rows of a video plane without the padding at the end of each - None for
+    /// audio, or as for plane()
+    pub fn rows(&self, index: usize) -> Option<RowIter> {
+        let row_len = self.row_len(index)?;
+        let line_size = self.line_size(index);
+        if line_size < row_len {
+            return None;
+        }
+
+        self.plane(index).map(|plane| RowIter {
+            rows: plane.chunks(line_size),
+            row_len,
+        })
+    }
+
+    /// Iterates over the rows of a video plane for writing - None as for rows() and plane_mut()
+    pub fn rows_mut(&mut self, index: usize) -> Option<RowIterMut> {
+        let row_len = self.row_len(index)?;
+        let line_size = self.line_size(index);
+        if line_size < row_len {
+            return None;
+        }
+
+        self.plane_mut(index).map(|plane| RowIterMut {
+            rows: plane.chunks_mut(line_size),
+            row_len,
+        })
+    }
+
+    /// Returns the bytes of image data in each row of a video plane
+    fn row_len(&self, index: usize) -> Option<usize> {
+        if self.is_audio() {
+            return None;
+        }
+
+        unsafe {
+            match ff::av_image_get_linesize(self.pixel_format(), self.width() as i32, index as i32) {
+                len if 0 < len => Some(len as usize),
+                _ => None,
             }
         }
     }
@@ -147,21 +484,41 @@ impl Frame {
         }
     }
 
+    pub fn set_content_light_level(&mut self, value: &ContentLightLevel) {
+        self.set_side_data(ff::AVFrameSideDataType::AV_FRAME_DATA_CONTENT_LIGHT_LEVEL, &value.to_bytes());
+    }
+
+    pub fn set_display_rotation(&mut self, value: f64) {
+        self.set_side_data(ff::AVFrameSideDataType::AV_FRAME_DATA_DISPLAYMATRIX, &side_data::display_matrix(value));
+    }
+
     pub fn set_height(&self, value: usize) {
         unsafe {
             (*self.0).height = value as i32;
         }
     }
 
+    pub fn set_mastering_display(&mut self, value: &MasteringDisplay) {
+        self.set_side_data(ff::AVFrameSideDataType::AV_FRAME_DATA_MASTERING_DISPLAY_METADATA, &value.to_bytes());
+    }
+
     pub fn set_pixel_format(&self, value: ff::AVPixelFormat) {
         unsafe {
             (*self.0).format = value as i32;
         }
     }
 
-    pub fn set_pts(&mut self, value: usize) {
+    /// Sets the pts, rescaled to the time base of the frame - a frame without a time base takes
+    /// on that of the timestamp
+    pub fn set_pts(&mut self, value: Option<Timestamp>) {
+        if let Some(value) = value {
+            if 0 == self.1.num {
+                self.1 = value.time_base;
+            }
+        }
+
         unsafe {
-            (*self.0).pts = value as i64;
+            (*self.0).pts = Timestamp::into_raw(value.map(|value| value.rescale(self.1)));
         }
     }
 
@@ -183,12 +540,68 @@ impl Frame {
         }
     }
 
+    /// Adds side data of the given type, replacing any the frame already has
+    pub fn set_side_data(&mut self, ty: ff::AVFrameSideDataType, data: &[u8]) {
+        unsafe {
+            ff::av_frame_remove_side_data(self.0, ty);
+            match ff::av_frame_new_side_data(self.0, ty, data.len() as i32) {
+                side_data if side_data.is_null() => panic!("out of memory"),
+                side_data => copy_nonoverlapping(data.as_ptr(), (*side_data).data, data.len()),
+            }
+        }
+    }
+
+    /// Sets the time base the timestamps are counted in, without changing their values
+    pub fn set_time_base(&mut self, value: Rational) {
+        self.1 = value;
+    }
+
     pub fn set_width(&self, value: usize) {
         unsafe {
             (*self.0).width = value as i32;
         }
     }
 
+    /// Returns the side data of the given type, if the frame has any
+    pub fn side_data(&self, ty: ff::AVFrameSideDataType) -> Option<&[u8]> {
+        unsafe {
+            match ff::av_frame_get_side_data(self.0, ty) {
+                side_data if side_data.is_null() => None,
+                side_data => Some(side_data::bytes((*side_data).data, (*side_data).size)),
+            }
+        }
+    }
+
+    pub fn side_data_iter(&self) -> FrameSideDataIter {
+        unsafe {
+            FrameSideDataIter::new((*self.0).side_data, (*self.0).nb_side_data as usize)
+        }
+    }
+
+    /// Moves the data and properties into a new frame, leaving this one empty
+    pub fn take(&mut self) -> Self {
+        let mut frame = Self::alloc();
+        frame.1 = self.1;
+        unsafe {
+            ff::av_frame_move_ref(frame.0, self.0);
+        }
+
+        frame
+    }
+
+    pub fn time_base(&self) -> Rational {
+        self.1
+    }
+
+    /// Reads a raw timestamp of the frame - None while the time base is unknown, as the value
+    /// means nothing without it
+    fn timestamp(&self, value: i64) -> Option<Timestamp> {
+        match self.1.num {
+            0 => None,
+            _ => Timestamp::from_raw(value, self.1),
+        }
+    }
+
     pub fn width(&self) -> usize {
         unsafe {
             (*self.0).width as usize
@@ -196,6 +609,100 @@ impl Frame {
     }
 }
 
+/// Iterates over the rows of a video plane, without their padding
+pub struct RowIter<'a> {
+    rows: Chunks<'a, u8>,
+    row_len: usize,
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        let row_len = self.row_len;
+        self.rows.next().map(|row| &row[..row_len])
+    }
+}
+
+/// Iterates over the rows of a video plane for writing, without their padding
+pub struct RowIterMut<'a> {
+    rows: ChunksMut<'a, u8>,
+    row_len: usize,
+}
+
+impl<'a> Iterator for RowIterMut<'a> {
+    type Item = &'a mut [u8];
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        let row_len = self.row_len;
+        self.rows.next().map(|row| &mut row[..row_len])
+    }
+}
+
+/// A type the samples of an audio frame can be viewed as
+///
+/// Unsafe to implement: the sample formats must hold values of exactly this type
+pub unsafe trait Sample: Copy {
+    /// Returns the packed and planar sample formats of this type
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat);
+}
+
+unsafe impl Sample for u8 {
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat) {
+        (ff::AVSampleFormat::AV_SAMPLE_FMT_U8, ff::AVSampleFormat::AV_SAMPLE_FMT_U8P)
+    }
+}
+
+unsafe impl Sample for i16 {
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat) {
+        (ff::AVSampleFormat::AV_SAMPLE_FMT_S16, ff::AVSampleFormat::AV_SAMPLE_FMT_S16P)
+    }
+}
+
+unsafe impl Sample for i32 {
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat) {
+        (ff::AVSampleFormat::AV_SAMPLE_FMT_S32, ff::AVSampleFormat::AV_SAMPLE_FMT_S32P)
+    }
+}
+
+unsafe impl Sample for i64 {
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat) {
+        (ff::AVSampleFormat::AV_SAMPLE_FMT_S64, ff::AVSampleFormat::AV_SAMPLE_FMT_S64P)
+    }
+}
+
+unsafe impl Sample for f32 {
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat) {
+        (ff::AVSampleFormat::AV_SAMPLE_FMT_FLT, ff::AVSampleFormat::AV_SAMPLE_FMT_FLTP)
+    }
+}
+
+unsafe impl Sample for f64 {
+    fn sample_formats() -> (ff::AVSampleFormat, ff::AVSampleFormat) {
+        (ff::AVSampleFormat::AV_SAMPLE_FMT_DBL, ff::AVSampleFormat::AV_SAMPLE_FMT_DBLP)
+    }
+}
+
+impl Clone for Frame {
+    /// Makes another reference to the same buffers, which is cheap - use deep_copy() or
+    /// make_writable() before changing the data of either frame
+    fn clone(&self) -> Self {
+        unsafe {
+            // av_frame_ref cannot reference an empty frame, so clone its properties only
+            if (*self.0).data[0].is_null() {
+                return self.deep_copy().expect("out of memory");
+            }
+
+            let mut frame = Self::alloc();
+            frame.1 = self.1;
+            match ff::av_frame_ref(frame.0, self.0) {
+                e if 0 > e => panic!("out of memory"),
+                _ => frame,
+            }
+        }
+    }
+}
+
 impl Deref for Frame {
     type Target = ff::AVFrame;
 
@@ -221,4 +728,7 @@ impl Drop for Frame {
             ff::av_frame_free(&mut self.0);
         }
     }
-}
\ No newline at end of file
+}
+
+// The buffers are reference counted atomically, so a frame may be dropped or cloned on any thread
+unsafe impl Send for Frame {}
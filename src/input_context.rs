@@ -0,0 +1,263 @@
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::ptr::{null, null_mut};
+
+use libc::c_void;
+
+use ::{Codec, cstring_from_path, Dictionary, error_with_path, ff, FFmpegError, InputFormat, Options, Packet, Rational, Stream, Timestamp};
+use io_context::IoContext;
+
+/// A demuxer reading from a file or reader - the file or reader is closed when dropped
+pub struct InputContext(*mut ff::AVFormatContext, Option<IoContext>, Option<PathBuf>);
+
+impl InputContext {
+    /// Opens a file for reading, probing for the input format
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, FFmpegError> {
+        Self::open_input(Some(filename.as_ref()), null_mut(), None, None)
+    }
+
+    /// Opens a file for reading using the given input format
+    pub fn open_as<P: AsRef<Path>>(filename: P, format: &mut InputFormat) -> Result<Self, FFmpegError> {
+        Self::open_input(Some(filename.as_ref()), &mut **format, None, None)
+    }
+
+    /// Opens a seekable reader, probing for the input format
+    pub fn open_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self, FFmpegError> {
+        Self::open_input(None, null_mut(), Some(IoContext::seekable_reader(reader)), None)
+    }
+
+    /// Opens a seekable reader using the given input format
+    pub fn open_reader_as<R: Read + Seek + Send + 'static>(reader: R, format: &mut InputFormat) -> Result<Self, FFmpegError> {
+        Self::open_input(None, &mut **format, Some(IoContext::seekable_reader(reader)), None)
+    }
+
+    /// Opens a reader which cannot seek, such as a socket, probing for the input format
+    pub fn open_sequential_reader<R: Read + Send + 'static>(reader: R) -> Result<Self, FFmpegError> {
+        Self::open_input(None, null_mut(), Some(IoContext::reader(reader)), None)
+    }
+
+    /// Opens a reader which cannot seek, such as a socket, using the given input format
+    pub fn open_sequential_reader_as<R: Read + Send + 'static>(reader: R, format: &mut InputFormat) -> Result<Self, FFmpegError> {
+        Self::open_input(None, &mut **format, Some(IoContext::reader(reader)), None)
+    }
+
+    /// Opens a file for reading, optionally using the given input format - on return the
+    /// options hold the entries which neither the demuxer nor the stream decoders recognized
+    pub fn open_with_options<P: AsRef<Path>>(filename: P, format: Option<&mut InputFormat>, options: &mut Dictionary) -> Result<Self, FFmpegError> {
+        Self::open_input(Some(filename.as_ref()), format_ptr(format), None, Some(options))
+    }
+
+    /// Opens a seekable reader, optionally using the given input format - on return the
+    /// options hold the entries which neither the demuxer nor the stream decoders recognized
+    pub fn open_reader_with_options<R: Read + Seek + Send + 'static>(reader: R, format: Option<&mut InputFormat>, options: &mut Dictionary) -> Result<Self, FFmpegError> {
+        Self::open_input(None, format_ptr(format), Some(IoContext::seekable_reader(reader)), Some(options))
+    }
+
+    /// Opens a reader which cannot seek, optionally using the given input format - on return the
+    /// options hold the entries which neither the demuxer nor the stream decoders recognized
+    pub fn open_sequential_reader_with_options<R: Read + Send + 'static>(reader: R, format: Option<&mut InputFormat>, options: &mut Dictionary) -> Result<Self, FFmpegError> {
+        Self::open_input(None, format_ptr(format), Some(IoContext::reader(reader)), Some(options))
+    }
+
+    fn open_input(path: Option<&Path>, format: *mut ff::AVInputFormat, mut io: Option<IoContext>, mut options: Option<&mut Dictionary>) -> Result<Self, FFmpegError> {
+        let path = path.map(Path::to_path_buf);
+        let filename = match path {
+            Some(ref path) => Some(cstring_from_path(path).map_err(|e| e.with_path(path))?),
+            None => None,
+        };
+        let url = filename.as_ref().map_or(null(), |filename| filename.as_ptr());
+        let mut format_context = null_mut();
+
+        unsafe {
+            // Custom I/O requires us to allocate the format context ourselves
+            if let Some(ref mut io) = io {
+                format_context = ff::avformat_alloc_context();
+                if format_context.is_null() {
+                    panic!("out of memory");
+                }
+
+                (*format_context).pb = &mut **io;
+            }
+
+            // Open the file (allocates format context for us, or frees ours on failure)
+            let options_ptr: *mut *mut ff::AVDictionary = match options {
+                Some(ref mut options) => options.as_mut_ptr(),
+                None => null_mut(),
+            };
+            match ff::avformat_open_input(&mut format_context, url, format, options_ptr) {
+                0 => (),
+                e => return Err(error_with_path(e, "avformat_open_input", &path)),
+            };
+
+            // From here on the file is closed when the context is dropped
+            let input_context = InputContext(format_context, io, path);
+
+            let format_name = InputFormat::from((*format_context).iformat).name().to_owned();
+            match filename {
+                Some(ref filename) => debug!("Opening {:?} using input format {}", filename, format_name),
+                None => debug!("Opening reader using input format {}", format_name),
+            }
+
+            // Make sure there is a header or stream info we can read
+            let result = match options {
+                Some(options) => find_stream_info_with_options(format_context, options),
+                None => ff::avformat_find_stream_info(format_context, null_mut()),
+            };
+            if 0 > result {
+                return Err(input_context.error(result, "avformat_find_stream_info"));
+            }
+
+            Ok(input_context)
+        }
+    }
+
+    /// Adds the file and failed call to an error code
+    fn error(&self, err: i32, operation: &'static str) -> FFmpegError {
+        error_with_path(err, operation, &self.2)
+    }
+
+    pub fn find_best_stream(&self, ty: ff::AVMediaType) -> Result<Option<(Codec, usize)>, FFmpegError> {
+        let mut ptr = null_mut();
+        unsafe {
+            match ff::av_find_best_stream(self.0, ty, -1, -1, &mut ptr, 0) {
+                index if -1 < index && !ptr.is_null() => Ok(Some((Codec::from(ptr as *const _), index as usize))),
+                ff::AVERROR_STREAM_NOT_FOUND => Ok(None),
+                e => Err(self.error(e, "av_find_best_stream")),
+            }
+        }
+    }
+
+    pub fn flags(&self) -> i32 {
+        unsafe {
+            (*self.0).flags
+        }
+    }
+
+    /// Reads one frame - the returned packet owns its data and stays valid after the next read
+    pub fn read_frame(&mut self) -> Result<Option<Packet>, FFmpegError> {
+        let mut packet = Packet::alloc();
+        unsafe {
+            match ff::av_read_frame(self.0, &mut *packet) {
+                ff::AVERROR_EOF => return Ok(None),
+                e if 0 > e => return Err(self.error(e, "av_read_frame")),
+                _ => (),
+            }
+
+            let time_base = (*(*(*self.0).streams.offset(packet.stream_index() as isize))).time_base;
+            packet.set_time_base(time_base.into());
+
+            // Packets which are not reference counted are only valid until the next read, so copy them
+            if packet.buf.is_null() {
+                let mut owned = Packet::alloc();
+                owned.set_time_base(packet.time_base());
+                match ff::av_packet_ref(&mut *owned, &*packet) {
+                    e if 0 > e => return Err(self.error(e, "av_packet_ref").with_stream_index(packet.stream_index())),
+                    _ => packet = owned,
+                }
+            }
+        }
+
+        Ok(Some(packet))
+    }
+
+    /// Seeks to a byte position
+    pub fn seek_byte(&mut self, stream_index: usize, byte: i64) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::av_seek_frame(self.0, stream_index as i32, byte, ff::AVSEEK_FLAG_BYTE) {
+                e if 0 > e => Err(self.error(e, "av_seek_frame").with_stream_index(stream_index)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Seeks to a timestamp
+    pub fn seek_frame(&mut self, stream_index: usize, ts: i64) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::av_seek_frame(self.0, stream_index as i32, ts, ff::AVSEEK_FLAG_FRAME | ff::AVSEEK_FLAG_BACKWARD) {
+                e if 0 > e => Err(self.error(e, "av_seek_frame").with_stream_index(stream_index)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Returns the presentation time of the first frame of any stream, if it is known
+    pub fn start_time(&self) -> Option<Timestamp> {
+        unsafe {
+            Timestamp::from_raw((*self.0).start_time, Rational::new(1, ff::AV_TIME_BASE))
+        }
+    }
+
+    /// Returns the given stream, or None if there is no stream at that index
+    pub fn stream(&self, index: usize) -> Option<Stream> {
+        if self.stream_count() > index {
+            unsafe {
+                Some(Stream::from(*(*self.0).streams.offset(index as isize)))
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn stream_count(&self) -> usize {
+        unsafe {
+            (*self.0).nb_streams as usize
+        }
+    }
+}
+
+unsafe impl Options for InputContext {
+    fn options_ptr(&self) -> *mut c_void {
+        self.0 as *mut c_void
+    }
+}
+
+impl Drop for InputContext {
+    #[inline]
+    fn drop(&mut self) {
+        // Any custom I/O is freed after this, once the format context no longer uses it
+        unsafe {
+            ff::avformat_close_input(&mut self.0);
+        }
+    }
+}
+
+unsafe impl Send for InputContext {}
+
+fn format_ptr(format: Option<&mut InputFormat>) -> *mut ff::AVInputFormat {
+    match format {
+        Some(format) => &mut **format,
+        None => null_mut(),
+    }
+}
+
+/// Probes the streams, giving the decoder of each stream its own copy of the options - on return
+/// the options hold only the entries which none of the decoders recognized
+unsafe fn find_stream_info_with_options(format_context: *mut ff::AVFormatContext, options: &mut Dictionary) -> i32 {
+    let stream_count = (*format_context).nb_streams as usize;
+    if 0 == stream_count {
+        return ff::avformat_find_stream_info(format_context, null_mut());
+    }
+
+    let mut stream_options: Vec<Dictionary> = (0..stream_count).map(|_| options.clone()).collect();
+    let mut stream_option_ptrs: Vec<*mut ff::AVDictionary> = stream_options.iter_mut().map(|stream_options| *stream_options.as_mut_ptr()).collect();
+    let result = ff::avformat_find_stream_info(format_context, stream_option_ptrs.as_mut_ptr());
+
+    // Each decoder replaces its dictionary with the entries it did not consume
+    for (stream_options, ptr) in stream_options.iter_mut().zip(stream_option_ptrs) {
+        *stream_options.as_mut_ptr() = ptr;
+    }
+
+    if 0 > result {
+        return result;
+    }
+
+    let consumed: Vec<String> = options.iter()
+                                       .filter(|&(key, _)| stream_options.iter().any(|stream_options| stream_options.get(key).is_none()))
+                                       .map(|(key, _)| key.to_owned())
+                                       .collect();
+    for key in consumed {
+        options.remove(&key);
+    }
+
+    result
+}
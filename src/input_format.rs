@@ -2,6 +2,7 @@ use std::ops::{Deref, DerefMut};
 use std::ptr::null_mut;
 
 use ::{ff, str_from_utf8_cstr_unchecked};
+use options::{list as option_list, OptionIter};
 
 pub fn list() -> Vec<InputFormat> {
     let mut result = vec![];
@@ -27,6 +28,13 @@ impl InputFormat {
             str_from_utf8_cstr_unchecked((*self.0).name)
         }
     }
+
+    /// Returns the private options of this format
+    pub fn options(&self) -> OptionIter {
+        unsafe {
+            option_list((*self.0).priv_class)
+        }
+    }
 }
 
 impl From<*mut ff::AVInputFormat> for InputFormat {
@@ -52,3 +60,8 @@ impl DerefMut for InputFormat {
         }
     }
 }
+
+// Registered formats are static, read-only tables
+unsafe impl Send for InputFormat {}
+
+unsafe impl Sync for InputFormat {}
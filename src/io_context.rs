@@ -0,0 +1,200 @@
+use std::any::Any;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::mem::replace;
+use std::ops::{Deref, DerefMut};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice::{from_raw_parts, from_raw_parts_mut};
+
+use libc::{c_int, c_void, EINVAL, EIO, SEEK_CUR, SEEK_END, SEEK_SET};
+
+use ::{ff, FFmpegError};
+
+const BUFFER_SIZE: usize = 32 * 1024;
+
+type PacketFn = unsafe extern "C" fn(*mut c_void, *mut u8, c_int) -> c_int;
+type SeekFn = unsafe extern "C" fn(*mut c_void, i64, c_int) -> i64;
+type FlushFn = unsafe fn(*mut c_void) -> c_int;
+
+/// Custom I/O which lets FFmpeg read from a Rust reader or write to a Rust writer - owns the reader or writer
+pub struct IoContext {
+    ptr: *mut ff::AVIOContext,
+    opaque: Box<dyn Any + Send>,
+    flush: Option<FlushFn>,
+}
+
+impl IoContext {
+    pub fn reader<R: Read + Send + 'static>(reader: R) -> Self {
+        Self::alloc(reader, 0, Some(read_packet::<R>), None, None)
+    }
+
+    pub fn seekable_reader<R: Read + Seek + Send + 'static>(reader: R) -> Self {
+        Self::alloc(reader, 0, Some(read_packet::<R>), None, Some(seek::<R>))
+    }
+
+    pub fn writer<W: Write + Send + 'static>(writer: W) -> Self {
+        let mut io = Self::alloc(writer, 1, None, Some(write_packet::<W>), None);
+        io.flush = Some(flush_writer::<W>);
+        io
+    }
+
+    pub fn seekable_writer<W: Write + Seek + Send + 'static>(writer: W) -> Self {
+        let mut io = Self::alloc(writer, 1, None, Some(write_packet::<W>), Some(seek::<W>));
+        io.flush = Some(flush_writer::<W>);
+        io
+    }
+
+    fn alloc<T: Send + 'static>(opaque: T, write_flag: c_int, read: Option<PacketFn>, write: Option<PacketFn>, seek: Option<SeekFn>) -> Self {
+        // The opaque value is boxed so the pointer handed to FFmpeg stays put
+        let mut opaque = Box::new(opaque);
+        let opaque_ptr = &mut *opaque as *mut T as *mut c_void;
+
+        unsafe {
+            let buffer = ff::av_malloc(BUFFER_SIZE) as *mut u8;
+            if buffer.is_null() {
+                panic!("out of memory");
+            }
+
+            match ff::avio_alloc_context(buffer, BUFFER_SIZE as c_int, write_flag, opaque_ptr, read, write, seek) {
+                ptr if !ptr.is_null() => IoContext {
+                    ptr,
+                    opaque,
+                    flush: None,
+                },
+                _ => {
+                    ff::av_free(buffer as *mut c_void);
+                    panic!("out of memory");
+                }
+            }
+        }
+    }
+
+    /// Writes out anything FFmpeg has buffered and flushes the writer, returning the first error
+    /// either of them has run into
+    pub fn flush(&mut self) -> Result<(), FFmpegError> {
+        unsafe {
+            ff::avio_flush(self.ptr);
+            match (*self.ptr).error {
+                e if 0 > e => return Err(FFmpegError::from(e).with_operation("avio_flush")),
+                _ => (),
+            }
+
+            match self.flush {
+                Some(flush) => match flush((*self.ptr).opaque) {
+                    e if 0 > e => Err(FFmpegError::from(e).with_operation("Write::flush")),
+                    _ => Ok(()),
+                },
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Frees the I/O context and gives back the reader or writer, if it is a T
+    pub fn into_inner<T: 'static>(mut self) -> Option<T> {
+        let opaque = replace(&mut self.opaque, Box::new(()));
+        drop(self);
+
+        opaque.downcast::<T>().ok().map(|opaque| *opaque)
+    }
+}
+
+impl Deref for IoContext {
+    type Target = ff::AVIOContext;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            &*self.ptr
+        }
+    }
+}
+
+impl DerefMut for IoContext {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            &mut *self.ptr
+        }
+    }
+}
+
+impl Drop for IoContext {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            // FFmpeg may have swapped out the buffer we allocated, so free whichever one it holds now
+            ff::av_freep(&mut (*self.ptr).buffer as *mut *mut u8 as *mut c_void);
+            ff::avio_context_free(&mut self.ptr);
+        }
+    }
+}
+
+// The reader or writer is required to be Send
+unsafe impl Send for IoContext {}
+
+fn averror_from_io_error(err: &io::Error) -> c_int {
+    ff::AVERROR(err.raw_os_error().unwrap_or(EIO))
+}
+
+unsafe extern "C" fn read_packet<R: Read>(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut R);
+    let buf = from_raw_parts_mut(buf, buf_size as usize);
+
+    // A panic must not unwind into FFmpeg, so it is reported as an I/O error instead
+    catch_unwind(AssertUnwindSafe(|| {
+        loop {
+            match reader.read(buf) {
+                Ok(0) => return ff::AVERROR_EOF,
+                Ok(len) => return len as c_int,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(ref e) => return averror_from_io_error(e),
+            }
+        }
+    })).unwrap_or_else(|_| ff::AVERROR(EIO))
+}
+
+unsafe extern "C" fn write_packet<W: Write>(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let writer = &mut *(opaque as *mut W);
+    let buf = from_raw_parts(buf, buf_size as usize);
+
+    // FFmpeg records a failed write on the context and reports it from the next mux call
+    catch_unwind(AssertUnwindSafe(|| {
+        match writer.write_all(buf) {
+            Ok(()) => buf_size,
+            Err(ref e) => averror_from_io_error(e),
+        }
+    })).unwrap_or_else(|_| ff::AVERROR(EIO))
+}
+
+unsafe fn flush_writer<W: Write>(opaque: *mut c_void) -> c_int {
+    let writer = &mut *(opaque as *mut W);
+    catch_unwind(AssertUnwindSafe(|| {
+        match writer.flush() {
+            Ok(()) => 0,
+            Err(ref e) => averror_from_io_error(e),
+        }
+    })).unwrap_or_else(|_| ff::AVERROR(EIO))
+}
+
+unsafe extern "C" fn seek<S: Seek>(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let stream = &mut *(opaque as *mut S);
+    catch_unwind(AssertUnwindSafe(|| {
+        let result = match whence & !ff::AVSEEK_FORCE {
+            ff::AVSEEK_SIZE => stream_len(stream),
+            SEEK_SET => stream.seek(SeekFrom::Start(offset as u64)),
+            SEEK_CUR => stream.seek(SeekFrom::Current(offset)),
+            SEEK_END => stream.seek(SeekFrom::End(offset)),
+            _ => return ff::AVERROR(EINVAL) as i64,
+        };
+
+        match result {
+            Ok(pos) => pos as i64,
+            Err(ref e) => averror_from_io_error(e) as i64,
+        }
+    })).unwrap_or_else(|_| ff::AVERROR(EIO) as i64)
+}
+
+fn stream_len<S: Seek>(stream: &mut S) -> io::Result<u64> {
+    let pos = stream.seek(SeekFrom::Current(0))?;
+    let len = stream.seek(SeekFrom::End(0))?;
+    stream.seek(SeekFrom::Start(pos))?;
+
+    Ok(len)
+}
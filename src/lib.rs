@@ -1,77 +1,68 @@
 #![deny(warnings)]
 
+#[macro_use]
+extern crate bitflags;
 pub extern crate ffmpeg_sys as ff;
 extern crate libc;
+#[macro_use]
+extern crate log;
+#[cfg(feature = "bigrational")]
 pub extern crate num;
 
+mod audio_fifo;
 mod codec;
 mod codec_context;
+mod codec_descriptor;
 mod codec_params;
-mod format_context;
+mod dictionary;
+mod filter_graph;
 mod frame;
+mod input_context;
 mod input_format;
+mod io_context;
+mod logging;
+mod options;
+mod output_context;
 mod output_format;
 mod packet;
+mod rational;
+mod side_data;
 mod stream;
+mod swr_context;
 mod sws_context;
+mod timestamp;
 
+use std::error::Error;
 use std::ffi::{CStr, CString};
+use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::os::raw::c_char;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::from_utf8_unchecked;
 
-use num::{BigInt, BigRational, FromPrimitive, ToPrimitive};
-
-pub use self::codec::Codec;
+pub use self::audio_fifo::AudioFifo;
+pub use self::codec::{Codec, CodecCapabilities, Profile, list as codec_list};
 pub use self::codec_context::CodecContext;
+pub use self::codec_descriptor::{CodecDescriptor, CodecProperties};
 pub use self::codec_params::CodecParams;
-pub use self::format_context::FormatContext;
-pub use self::frame::Frame;
+pub use self::dictionary::{Dictionary, DictionaryIter};
+pub use self::filter_graph::{BufferSink, BufferSource, FilterGraph};
+pub use self::frame::{Frame, RowIter, RowIterMut, Sample};
+pub use self::input_context::InputContext;
 pub use self::input_format::{InputFormat, list as input_format_list};
+pub use self::logging::{init_logging, set_log_level};
+pub use self::options::{OptionInfo, OptionIter, Options, OptionValue};
+pub use self::output_context::OutputContext;
 pub use self::output_format::{OutputFormat, list as output_format_list};
-pub use self::packet::Packet;
+pub use self::packet::{Packet, PacketFlags};
+pub use self::rational::Rational;
+pub use self::side_data::{ContentLightLevel, FrameSideDataIter, MasteringDisplay, PacketSideDataIter, display_matrix, display_rotation};
 pub use self::stream::Stream;
+pub use self::swr_context::SwrContext;
 pub use self::sws_context::SwsContext;
+pub use self::timestamp::{Rounding, Timestamp, TimestampRemapper};
 
 use self::FFmpegError::*;
 
-pub fn avrational_from_bigrational(value: &BigRational) -> Result<ff::AVRational, FFmpegError> {
-    let reduced = reduce_bigrational_to_i32(value);
-    let numer = reduced.numer().to_i32();
-    let denom = reduced.denom().to_i32();
-
-    if numer.is_none() || denom.is_none() {
-        Err(FFmpegError::InvalidData)
-    } else {
-        Ok(ff::AVRational {
-            num: numer.unwrap(),
-            den: denom.unwrap(),
-        })
-    }
-}
-
-pub fn bigrational_from_avrational(value: &ff::AVRational) -> Result<BigRational, FFmpegError> {
-    let numer = BigInt::from_i32(value.num);
-    let denom = BigInt::from_i32(value.den);
-
-    if numer.is_none() || denom.is_none() {
-        Err(FFmpegError::InvalidData)
-    } else {
-        Ok(BigRational::new(numer.unwrap(), denom.unwrap()))
-    }
-}
-
-pub fn reduce_bigrational_to_i32(value: &BigRational) -> BigRational {
-    let mut result = value.clone();
-    let ten = BigInt::from_usize(10).unwrap();
-
-    while result.numer().to_i32().is_none() || result.denom().to_i32().is_none() {
-        result = BigRational::from((result.numer() / &ten, result.denom() / &ten));
-    }
-
-    result
-}
-
 pub fn cstring_from_path<P: AsRef<Path>>(path: P) -> Result<CString, FFmpegError> {
     let path_str = path.as_ref().as_os_str().to_str();
     if path_str.is_some() {
@@ -84,9 +75,19 @@ pub fn cstring_from_path<P: AsRef<Path>>(path: P) -> Result<CString, FFmpegError
     Err(FFmpegError::InvalidData)
 }
 
+/// Makes an error from an AVERROR code, recording the failed call and the file if there is one
+fn error_with_path(err: i32, operation: &'static str, path: &Option<PathBuf>) -> FFmpegError {
+    let err = FFmpegError::from(err).with_operation(operation);
+    match *path {
+        Some(ref path) => err.with_path(path),
+        None => err,
+    }
+}
+
 pub fn init() {
     unsafe {
         ff::av_register_all();
+        ff::avfilter_register_all();
     }
 }
 
@@ -127,11 +128,128 @@ pub enum FFmpegError {
     StreamNotFound,
 
     // libc errors
+    Again, // AKA Resource temporarily unavailable
+    BrokenPipe,
+    InvalidArgument,
+    Io,
     NoEntry, // AKA File not found
+    OutOfMemory,
+    PermissionDenied,
 
     Unknown(i32),
+
+    // Another error along with where it happened - use kind() to match on the underlying error
+    WithContext(Box<FFmpegError>, ErrorContext),
 }
 
+impl FFmpegError {
+    /// Returns the AVERROR code this error was made from
+    pub fn code(&self) -> i32 {
+        match *self {
+            WithContext(ref kind, _) => kind.code(),
+            BitStreamFilterNotFound => ff::AVERROR_BSF_NOT_FOUND,
+            BufferTooSmall => ff::AVERROR_BUFFER_TOO_SMALL,
+            Bug => ff::AVERROR_BUG,
+            Bug2 => ff::AVERROR_BUG2,
+            DecoderNotFound => ff::AVERROR_DECODER_NOT_FOUND,
+            DemuxerNotFound => ff::AVERROR_DEMUXER_NOT_FOUND,
+            EncoderNotFound => ff::AVERROR_ENCODER_NOT_FOUND,
+            EOF => ff::AVERROR_EOF,
+            Exit => ff::AVERROR_EXIT,
+            Experimental => ff::AVERROR_EXPERIMENTAL,
+            External => ff::AVERROR_EXTERNAL,
+            FilterNotFound => ff::AVERROR_FILTER_NOT_FOUND,
+            HttpBadRequest => ff::AVERROR_HTTP_BAD_REQUEST,
+            HttpForbidden => ff::AVERROR_HTTP_FORBIDDEN,
+            HttpNotFound => ff::AVERROR_HTTP_NOT_FOUND,
+            HttpOther4xx => ff::AVERROR_HTTP_OTHER_4XX,
+            HttpServerError => ff::AVERROR_HTTP_SERVER_ERROR,
+            HttpUnauthorized => ff::AVERROR_HTTP_UNAUTHORIZED,
+            InputChanged => ff::AVERROR_INPUT_CHANGED,
+            InvalidData => ff::AVERROR_INVALIDDATA,
+            MuxerNotFound => ff::AVERROR_MUXER_NOT_FOUND,
+            OptionNotFound => ff::AVERROR_OPTION_NOT_FOUND,
+            OutputChanged => ff::AVERROR_OUTPUT_CHANGED,
+            PatchWelcome => ff::AVERROR_PATCHWELCOME,
+            ProtocolNotFound => ff::AVERROR_PROTOCOL_NOT_FOUND,
+            StreamNotFound => ff::AVERROR_STREAM_NOT_FOUND,
+            Again => ff::AVERROR(libc::EAGAIN),
+            BrokenPipe => ff::AVERROR(libc::EPIPE),
+            InvalidArgument => ff::AVERROR(libc::EINVAL),
+            Io => ff::AVERROR(libc::EIO),
+            NoEntry => ff::AVERROR(libc::ENOENT),
+            OutOfMemory => ff::AVERROR(libc::ENOMEM),
+            PermissionDenied => ff::AVERROR(libc::EACCES),
+            Unknown(0) => ff::AVERROR_UNKNOWN,
+            Unknown(code) => code,
+        }
+    }
+
+    /// Returns where the error happened, if that is known
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match *self {
+            WithContext(_, ref context) => Some(context),
+            _ => None,
+        }
+    }
+
+    fn into_parts(self) -> (FFmpegError, ErrorContext) {
+        match self {
+            WithContext(kind, context) => (*kind, context),
+            kind => (kind, ErrorContext::default()),
+        }
+    }
+
+    /// Returns the underlying error without any context, for matching on
+    pub fn kind(&self) -> &FFmpegError {
+        match *self {
+            WithContext(ref kind, _) => kind,
+            ref kind => kind,
+        }
+    }
+
+    /// Records the FFmpeg call which failed
+    pub fn with_operation(self, operation: &'static str) -> Self {
+        let (kind, mut context) = self.into_parts();
+        context.operation = Some(operation);
+        WithContext(Box::new(kind), context)
+    }
+
+    /// Records the file which was being read or written
+    pub fn with_path<P: AsRef<Path>>(self, path: P) -> Self {
+        let (kind, mut context) = self.into_parts();
+        context.path = Some(path.as_ref().to_path_buf());
+        WithContext(Box::new(kind), context)
+    }
+
+    /// Records the stream which was being read or written
+    pub fn with_stream_index(self, stream_index: usize) -> Self {
+        let (kind, mut context) = self.into_parts();
+        context.stream_index = Some(stream_index);
+        WithContext(Box::new(kind), context)
+    }
+}
+
+impl Display for FFmpegError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if let WithContext(ref kind, ref context) = *self {
+            return write!(f, "{}: {}", context, kind);
+        }
+
+        let mut buf = [0 as c_char; ff::AV_ERROR_MAX_STRING_SIZE];
+
+        // av_strerror describes codes it does not know as well, so the result can be ignored
+        let message = unsafe {
+            ff::av_strerror(self.code(), buf.as_mut_ptr(), buf.len());
+            CStr::from_ptr(buf.as_ptr()).to_string_lossy()
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl Error for FFmpegError {}
+
 impl From<i32> for FFmpegError {
     fn from(err: i32) -> Self {
         match err {
@@ -162,8 +280,74 @@ impl From<i32> for FFmpegError {
             ff::AVERROR_PROTOCOL_NOT_FOUND => ProtocolNotFound,
             ff::AVERROR_STREAM_NOT_FOUND => StreamNotFound,
             ff::AVERROR_UNKNOWN => Unknown(0),
+            e if libc::EAGAIN == ff::AVUNERROR(e) => Again,
+            e if libc::EPIPE == ff::AVUNERROR(e) => BrokenPipe,
+            e if libc::EINVAL == ff::AVUNERROR(e) => InvalidArgument,
+            e if libc::EIO == ff::AVUNERROR(e) => Io,
             e if libc::ENOENT == ff::AVUNERROR(e) => NoEntry,
+            e if libc::ENOMEM == ff::AVUNERROR(e) => OutOfMemory,
+            e if libc::EACCES == ff::AVUNERROR(e) => PermissionDenied,
             _ => Unknown(err),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Where an error happened: the FFmpeg call, and the file and stream it was working on
+#[derive(Clone, Debug, Default)]
+pub struct ErrorContext {
+    pub operation: Option<&'static str>,
+    pub path: Option<PathBuf>,
+    pub stream_index: Option<usize>,
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} failed", self.operation.unwrap_or("operation"))?;
+
+        if let Some(ref path) = self.path {
+            write!(f, " on {}", path.display())?;
+        }
+
+        if let Some(stream_index) = self.stream_index {
+            write!(f, " for stream {}", stream_index)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Documents which types may cross threads - this fails to compile if any of them stops
+    /// being Send or Sync. Contexts are Send but not Sync: FFmpeg lets one move to another thread
+    /// but not be used by two at once
+    #[test]
+    fn thread_safety() {
+        fn send<T: Send>() {}
+        fn sync<T: Sync>() {}
+
+        send::<AudioFifo>();
+        send::<Codec>();
+        send::<CodecContext>();
+        send::<CodecDescriptor>();
+        send::<CodecParams>();
+        send::<Dictionary>();
+        send::<FilterGraph>();
+        send::<Frame>();
+        send::<InputContext>();
+        send::<InputFormat>();
+        send::<OutputContext>();
+        send::<OutputFormat>();
+        send::<Packet>();
+        send::<SwrContext>();
+        send::<SwsContext>();
+
+        sync::<Codec>();
+        sync::<CodecDescriptor>();
+        sync::<Dictionary>();
+        sync::<InputFormat>();
+        sync::<OutputFormat>();
+    }
+}
@@ -0,0 +1,95 @@
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use libc::{c_int, c_void};
+use log::{Level, LevelFilter};
+
+use ::ff;
+
+const LINE_SIZE: usize = 1024;
+
+thread_local! {
+    // av_log_format_line only adds the "[class @ 0x...]" prefix at the start of a line, and uses
+    // this to remember whether the last message ended one
+    static PRINT_PREFIX: Cell<c_int> = Cell::new(1);
+}
+
+/// Sends FFmpeg's log messages to the log crate instead of stderr - messages are logged with the
+/// name of the FFmpeg component which wrote them, such as "h264" or "mov,mp4,m4a,3gp,3g2,mj2", as
+/// the target
+pub fn init_logging() {
+    unsafe {
+        ff::av_log_set_callback(Some(log_callback));
+    }
+}
+
+/// Sets the most verbose level FFmpeg bothers to log at
+pub fn set_log_level(level: LevelFilter) {
+    let level = match level {
+        LevelFilter::Off => ff::AV_LOG_QUIET,
+        LevelFilter::Error => ff::AV_LOG_ERROR,
+        LevelFilter::Warn => ff::AV_LOG_WARNING,
+        LevelFilter::Info => ff::AV_LOG_INFO,
+        LevelFilter::Debug => ff::AV_LOG_VERBOSE,
+        LevelFilter::Trace => ff::AV_LOG_TRACE,
+    };
+
+    unsafe {
+        ff::av_log_set_level(level);
+    }
+}
+
+fn level_from_av_log(level: c_int) -> Option<Level> {
+    match level {
+        l if ff::AV_LOG_QUIET >= l => None,
+        l if ff::AV_LOG_ERROR >= l => Some(Level::Error),
+        l if ff::AV_LOG_WARNING >= l => Some(Level::Warn),
+        l if ff::AV_LOG_INFO >= l => Some(Level::Info),
+        l if ff::AV_LOG_VERBOSE >= l => Some(Level::Debug),
+        _ => Some(Level::Trace),
+    }
+}
+
+/// Returns the class name of the FFmpeg struct which logged a message, if it has one
+unsafe fn class_name<'a>(avcl: *mut c_void) -> Option<&'a str> {
+    // Anything passed to av_log starts with a pointer to its AVClass
+    if avcl.is_null() {
+        return None;
+    }
+
+    let class = *(avcl as *const *const ff::AVClass);
+    if class.is_null() || (*class).class_name.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr((*class).class_name).to_str().ok()
+}
+
+unsafe extern "C" fn log_callback(avcl: *mut c_void, av_level: c_int, fmt: *const c_char, vl: *mut ff::__va_list_tag) {
+    if av_level > ff::av_log_get_level() {
+        return;
+    }
+
+    let level = match level_from_av_log(av_level) {
+        Some(level) if log::max_level() >= level => level,
+        _ => return,
+    };
+
+    // A panicking logger must not unwind into FFmpeg, so the message is dropped instead
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let mut line = [0 as c_char; LINE_SIZE];
+        PRINT_PREFIX.with(|print_prefix| {
+            let mut prefix = print_prefix.get();
+            ff::av_log_format_line(avcl, av_level, fmt, vl, line.as_mut_ptr(), LINE_SIZE as c_int, &mut prefix);
+            print_prefix.set(prefix);
+        });
+
+        let line = CStr::from_ptr(line.as_ptr()).to_string_lossy();
+        let line = line.trim_end();
+        if !line.is_empty() {
+            log!(target: class_name(avcl).unwrap_or("ffmpeg"), level, "{}", line);
+        }
+    }));
+}
@@ -0,0 +1,287 @@
+use std::ffi::CString;
+use std::ptr::{null, null_mut};
+
+use libc::c_void;
+
+use ::{ff, FFmpegError, Rational, str_from_utf8_cstr_unchecked};
+
+/// Gets and sets AVOptions by name, searching private options such as the x264 "preset" or the
+/// fdk-aac "vbr" as well - misspelled names fail with OptionNotFound
+///
+/// Unsafe to implement because FFmpeg dereferences the pointer options_ptr() returns - it must
+/// point to a live struct whose first field is a pointer to its AVClass
+pub unsafe trait Options {
+    /// Returns the struct whose options are accessed, which must start with an AVClass pointer
+    fn options_ptr(&self) -> *mut c_void;
+
+    fn get_option(&self, name: &str) -> Result<String, FFmpegError> {
+        let name = CString::new(name).unwrap();
+        let mut value = null_mut();
+        unsafe {
+            match ff::av_opt_get(self.options_ptr(), name.as_ptr(), ff::AV_OPT_SEARCH_CHILDREN, &mut value) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => {
+                    let result = str_from_utf8_cstr_unchecked(value as *const _).to_owned();
+                    ff::av_free(value as *mut c_void);
+                    Ok(result)
+                }
+            }
+        }
+    }
+
+    /// Gets a binary option, such as the "cenc_decryption_key" of a demuxer
+    fn get_option_binary(&self, name: &str) -> Result<Vec<u8>, FFmpegError> {
+        let hex = self.get_option(name)?;
+
+        // av_opt_get gives binary options as a hex string, two digits per byte
+        (0..hex.len() / 2)
+            .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| FFmpegError::InvalidArgument))
+            .collect()
+    }
+
+    fn get_option_double(&self, name: &str) -> Result<f64, FFmpegError> {
+        let name = CString::new(name).unwrap();
+        let mut value = 0.0;
+        unsafe {
+            match ff::av_opt_get_double(self.options_ptr(), name.as_ptr(), ff::AV_OPT_SEARCH_CHILDREN, &mut value) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(value),
+            }
+        }
+    }
+
+    fn get_option_int(&self, name: &str) -> Result<i64, FFmpegError> {
+        let name = CString::new(name).unwrap();
+        let mut value = 0;
+        unsafe {
+            match ff::av_opt_get_int(self.options_ptr(), name.as_ptr(), ff::AV_OPT_SEARCH_CHILDREN, &mut value) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(value),
+            }
+        }
+    }
+
+    fn get_option_rational(&self, name: &str) -> Result<Rational, FFmpegError> {
+        let name = CString::new(name).unwrap();
+        let mut value = ff::AVRational {
+            num: 0,
+            den: 1,
+        };
+        unsafe {
+            match ff::av_opt_get_q(self.options_ptr(), name.as_ptr(), ff::AV_OPT_SEARCH_CHILDREN, &mut value) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(value.into()),
+            }
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), FFmpegError> {
+        let name = CString::new(name).unwrap();
+        let value = CString::new(value).unwrap();
+        unsafe {
+            match ff::av_opt_set(self.options_ptr(), name.as_ptr(), value.as_ptr(), ff::AV_OPT_SEARCH_CHILDREN) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn set_option_binary(&mut self, name: &str, value: &[u8]) -> Result<(), FFmpegError> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            match ff::av_opt_set_bin(self.options_ptr(), name.as_ptr(), value.as_ptr(), value.len() as i32, ff::AV_OPT_SEARCH_CHILDREN) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn set_option_double(&mut self, name: &str, value: f64) -> Result<(), FFmpegError> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            match ff::av_opt_set_double(self.options_ptr(), name.as_ptr(), value, ff::AV_OPT_SEARCH_CHILDREN) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn set_option_int(&mut self, name: &str, value: i64) -> Result<(), FFmpegError> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            match ff::av_opt_set_int(self.options_ptr(), name.as_ptr(), value, ff::AV_OPT_SEARCH_CHILDREN) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn set_option_rational(&mut self, name: &str, value: Rational) -> Result<(), FFmpegError> {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            match ff::av_opt_set_q(self.options_ptr(), name.as_ptr(), value.into(), ff::AV_OPT_SEARCH_CHILDREN) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// Lists the options declared by an AVClass, such as the private class of a codec or muxer,
+/// skipping the named constants which are listed by OptionInfo::constants()
+pub fn list(class: *const ff::AVClass) -> OptionIter {
+    OptionIter {
+        class,
+        prev: null(),
+    }
+}
+
+/// The default value of an option
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionValue {
+    Double(f64),
+    Int(i64),
+    Rational(Rational),
+    String(&'static str),
+}
+
+/// Describes one option, for display in settings screens and the like
+pub struct OptionInfo {
+    class: *const ff::AVClass,
+    ptr: *const ff::AVOption,
+}
+
+impl OptionInfo {
+    /// Returns the named constants this option accepts, for options with a unit
+    pub fn constants(&self) -> Vec<OptionInfo> {
+        let unit = match self.unit() {
+            Some(unit) => unit,
+            None => return vec![],
+        };
+
+        let mut result = vec![];
+        let mut prev = null();
+        unsafe {
+            loop {
+                prev = ff::av_opt_next(&self.class as *const _ as *const c_void, prev);
+                if prev.is_null() {
+                    break;
+                }
+
+                let option = OptionInfo {
+                    class: self.class,
+                    ptr: prev,
+                };
+                if option.is_constant() && Some(unit) == option.unit() {
+                    result.push(option);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the default value, or the value of a named constant - None for binary options
+    pub fn default_value(&self) -> Option<OptionValue> {
+        use ff::AVOptionType::*;
+
+        unsafe {
+            let default_val = &(*self.ptr).default_val;
+            match self.option_type() {
+                AV_OPT_TYPE_DOUBLE | AV_OPT_TYPE_FLOAT => Some(OptionValue::Double(default_val.dbl)),
+                AV_OPT_TYPE_RATIONAL => Some(OptionValue::Rational(default_val.q.into())),
+                AV_OPT_TYPE_STRING | AV_OPT_TYPE_IMAGE_SIZE | AV_OPT_TYPE_VIDEO_RATE | AV_OPT_TYPE_COLOR | AV_OPT_TYPE_DICT => match default_val.str {
+                    value if !value.is_null() => Some(OptionValue::String(str_from_utf8_cstr_unchecked(value))),
+                    _ => None,
+                },
+                AV_OPT_TYPE_BINARY => None,
+                _ => Some(OptionValue::Int(default_val.i64)),
+            }
+        }
+    }
+
+    pub fn flags(&self) -> i32 {
+        unsafe {
+            (*self.ptr).flags
+        }
+    }
+
+    pub fn help(&self) -> &'static str {
+        unsafe {
+            match (*self.ptr).help {
+                help if !help.is_null() => str_from_utf8_cstr_unchecked(help),
+                _ => "",
+            }
+        }
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.option_type() == ff::AVOptionType::AV_OPT_TYPE_CONST
+    }
+
+    pub fn max(&self) -> f64 {
+        unsafe {
+            (*self.ptr).max
+        }
+    }
+
+    pub fn min(&self) -> f64 {
+        unsafe {
+            (*self.ptr).min
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        unsafe {
+            str_from_utf8_cstr_unchecked((*self.ptr).name)
+        }
+    }
+
+    pub fn option_type(&self) -> ff::AVOptionType {
+        unsafe {
+            (*self.ptr).type_
+        }
+    }
+
+    /// Returns the unit which ties an option to its named constants
+    pub fn unit(&self) -> Option<&'static str> {
+        unsafe {
+            match (*self.ptr).unit {
+                unit if !unit.is_null() => Some(str_from_utf8_cstr_unchecked(unit)),
+                _ => None,
+            }
+        }
+    }
+}
+
+pub struct OptionIter {
+    class: *const ff::AVClass,
+    prev: *const ff::AVOption,
+}
+
+impl Iterator for OptionIter {
+    type Item = OptionInfo;
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.class.is_null() {
+            return None;
+        }
+
+        unsafe {
+            loop {
+                // av_opt_next wants a pointer to a struct which starts with the class pointer
+                self.prev = ff::av_opt_next(&self.class as *const _ as *const c_void, self.prev);
+                if self.prev.is_null() {
+                    return None;
+                }
+
+                let option = OptionInfo {
+                    class: self.class,
+                    ptr: self.prev,
+                };
+                if !option.is_constant() {
+                    return Some(option);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::ptr::{null, null_mut};
+
+use libc::c_void;
+
+use ::{cstring_from_path, Dictionary, error_with_path, ff, FFmpegError, Options, OutputFormat, Packet, Stream};
+use io_context::IoContext;
+
+/// A muxer writing to a file or writer - the file or writer is closed when dropped
+pub struct OutputContext(*mut ff::AVFormatContext, Option<IoContext>, Option<PathBuf>);
+
+impl OutputContext {
+    /// Opens a file for writing, guessing the output format from the file name
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, FFmpegError> {
+        Self::open_output(Some(filename.as_ref()), null_mut(), None)
+    }
+
+    /// Opens a file for writing using the given output format
+    pub fn open_as<P: AsRef<Path>>(filename: P, format: &mut OutputFormat) -> Result<Self, FFmpegError> {
+        Self::open_output(Some(filename.as_ref()), &mut **format, None)
+    }
+
+    /// Opens a seekable writer using the given output format
+    pub fn open_writer<W: Write + Seek + Send + 'static>(writer: W, format: &mut OutputFormat) -> Result<Self, FFmpegError> {
+        Self::open_output(None, &mut **format, Some(IoContext::seekable_writer(writer)))
+    }
+
+    /// Opens a writer which cannot seek, such as a socket, using the given output format - the
+    /// format must not need to seek back, for example mp4 requires fragmenting
+    pub fn open_sequential_writer<W: Write + Send + 'static>(writer: W, format: &mut OutputFormat) -> Result<Self, FFmpegError> {
+        Self::open_output(None, &mut **format, Some(IoContext::writer(writer)))
+    }
+
+    fn open_output(path: Option<&Path>, format: *mut ff::AVOutputFormat, io: Option<IoContext>) -> Result<Self, FFmpegError> {
+        let path = path.map(Path::to_path_buf);
+        let filename = match path {
+            Some(ref path) => Some(cstring_from_path(path).map_err(|e| e.with_path(path))?),
+            None => None,
+        };
+        let url = filename.as_ref().map_or(null(), |filename| filename.as_ptr());
+        let mut format_context = null_mut();
+
+        unsafe {
+            // Allocate the output context
+            match ff::avformat_alloc_output_context2(&mut format_context, format, null(), url) {
+                e if 0 > e || format_context.is_null() => return Err(error_with_path(e, "avformat_alloc_output_context2", &path)),
+                _ => (),
+            };
+
+            // From here on the context is freed when dropped
+            let mut output_context = OutputContext(format_context, io, path);
+
+            let format_name = OutputFormat::from((*format_context).oformat).name().to_owned();
+            match filename {
+                Some(ref filename) => debug!("Opening {:?} using output format {}", filename, format_name),
+                None => debug!("Opening writer using output format {}", format_name),
+            }
+
+            if let Some(ref mut io) = output_context.1 {
+                // Write through our own I/O
+                (*format_context).pb = &mut **io;
+                (*format_context).flags |= ff::AVFMT_FLAG_CUSTOM_IO;
+            } else if output_context.owns_file() {
+                // Open the file unless the muxer does its own I/O
+                match ff::avio_open(&mut (*format_context).pb, url, ff::AVIO_FLAG_WRITE) {
+                    e if 0 > e => return Err(output_context.error(e, "avio_open")),
+                    _ => (),
+                }
+            }
+
+            Ok(output_context)
+        }
+    }
+
+    /// Closes the file or flushes the writer, returning any error from doing so - dropping the
+    /// context also closes the file but ignores errors
+    pub fn close(mut self) -> Result<(), FFmpegError> {
+        if let Some(ref mut io) = self.1 {
+            return io.flush();
+        }
+
+        if self.owns_file() {
+            unsafe {
+                match ff::avio_closep(&mut (*self.0).pb) {
+                    e if 0 > e => return Err(self.error(e, "avio_closep")),
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flushes and gives back the writer this context was opened with, which must be a W
+    pub fn into_writer<W: Write + 'static>(mut self) -> Result<W, FFmpegError> {
+        let mut io = match self.1.take() {
+            Some(io) => io,
+            None => return Err(FFmpegError::InvalidData),
+        };
+
+        // The format context must not hold on to the I/O context once we have taken it
+        unsafe {
+            (*self.0).pb = null_mut();
+        }
+
+        drop(self);
+        io.flush()?;
+
+        io.into_inner().ok_or(FFmpegError::InvalidData)
+    }
+
+    /// Adds the file and failed call to an error code
+    fn error(&self, err: i32, operation: &'static str) -> FFmpegError {
+        error_with_path(err, operation, &self.2)
+    }
+
+    pub fn flags(&self) -> i32 {
+        unsafe {
+            (*self.0).flags
+        }
+    }
+
+    /// True if we opened a file which must be closed, rather than using custom I/O or a muxer
+    /// which does its own I/O
+    fn owns_file(&self) -> bool {
+        unsafe {
+            self.1.is_none() && 0 == (*(*self.0).oformat).flags & ff::AVFMT_NOFILE
+        }
+    }
+
+    /// Adds a new stream to the context
+    pub fn new_stream(&mut self) -> Result<Stream, FFmpegError> {
+        unsafe {
+            match ff::avformat_new_stream(self.0, null()) {
+                stream if !stream.is_null() => Ok(Stream::from(stream)),
+                _ => Err(FFmpegError::InvalidData),
+            }
+        }
+    }
+
+    /// Adds a new stream to the context
+    /*pub fn new_stream_as(&mut self, codec: &Codec) -> Result<Stream, FFmpegError> {
+        unsafe {
+            match ff::avformat_new_stream(self.0, **codec) {
+                stream if !stream.is_null() => Ok(Stream::from(stream)),
+                _ => Err(FFmpegError::InvalidData),
+            }
+        }
+    }*/
+
+    /// Returns the given stream, or None if there is no stream at that index
+    pub fn stream(&self, index: usize) -> Option<Stream> {
+        if self.stream_count() > index {
+            unsafe {
+                Some(Stream::from(*(*self.0).streams.offset(index as isize)))
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn stream_count(&self) -> usize {
+        unsafe {
+            (*self.0).nb_streams as usize
+        }
+    }
+
+    /// Writes file header
+    pub fn write_header(&mut self) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::avformat_write_header(self.0, null_mut()) {
+                e if 0 > e => Err(self.error(e, "avformat_write_header")),
+                _ => Ok(())
+            }
+        }
+    }
+
+    /// Writes file header using the given muxer options - on return the options hold the entries
+    /// which the muxer did not recognize
+    pub fn write_header_with_options(&mut self, options: &mut Dictionary) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::avformat_write_header(self.0, options.as_mut_ptr()) {
+                e if 0 > e => Err(self.error(e, "avformat_write_header")),
+                _ => Ok(())
+            }
+        }
+    }
+
+    /// Writes file data
+    pub fn write_interleaved(&mut self, packet: &mut Packet) -> Result<(), FFmpegError> {
+        // The muxer takes the packet contents, so note the stream beforehand for error reporting
+        let stream_index = packet.stream_index();
+        unsafe {
+            match ff::av_interleaved_write_frame(self.0, &mut **packet) {
+                e if 0 > e => Err(self.error(e, "av_interleaved_write_frame").with_stream_index(stream_index)),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Writes the file trailer
+    pub fn write_trailer(&mut self) -> Result<(), FFmpegError> {
+        unsafe {
+            match ff::av_write_trailer(self.0) {
+                e if 0 > e => Err(self.error(e, "av_write_trailer")),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+unsafe impl Options for OutputContext {
+    fn options_ptr(&self) -> *mut c_void {
+        self.0 as *mut c_void
+    }
+}
+
+impl Drop for OutputContext {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            if self.owns_file() {
+                ff::avio_closep(&mut (*self.0).pb);
+            }
+
+            ff::avformat_free_context(self.0);
+        }
+    }
+}
+
+unsafe impl Send for OutputContext {}
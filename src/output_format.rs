@@ -1,7 +1,9 @@
+use std::ffi::CString;
 use std::ops::{Deref, DerefMut};
-use std::ptr::null_mut;
+use std::ptr::{null, null_mut};
 
 use ::{ff, str_from_utf8_cstr_unchecked};
+use options::{list as option_list, OptionIter};
 
 pub fn list() -> Vec<OutputFormat> {
     let mut result = vec![];
@@ -22,11 +24,29 @@ pub fn list() -> Vec<OutputFormat> {
 pub struct OutputFormat(*mut ff::AVOutputFormat);
 
 impl OutputFormat {
+    /// Finds a registered output format by short name, such as "mp4" or "matroska"
+    pub fn find(short_name: &str) -> Option<Self> {
+        let short_name = CString::new(short_name).unwrap();
+        unsafe {
+            match ff::av_guess_format(short_name.as_ptr(), null(), null()) {
+                ptr if !ptr.is_null() => Some(OutputFormat(ptr)),
+                _ => None,
+            }
+        }
+    }
+
     pub fn name(&self) -> &str {
         unsafe {
             str_from_utf8_cstr_unchecked((*self.0).name)
         }
     }
+
+    /// Returns the private options of this format
+    pub fn options(&self) -> OptionIter {
+        unsafe {
+            option_list((*self.0).priv_class)
+        }
+    }
 }
 
 impl From<*mut ff::AVOutputFormat> for OutputFormat {
@@ -52,3 +72,8 @@ impl DerefMut for OutputFormat {
         }
     }
 }
+
+// Registered formats are static, read-only tables
+unsafe impl Send for OutputFormat {}
+
+unsafe impl Sync for OutputFormat {}
@@ -1,64 +1,248 @@
 use std::ops::{Deref, DerefMut};
+use std::ptr::copy_nonoverlapping;
+use std::slice::from_raw_parts;
 
-use ::ff;
+use libc::c_void;
 
-pub struct Packet(*mut ff::AVPacket);
+use ::{ContentLightLevel, ff, MasteringDisplay, PacketSideDataIter, Rational, Timestamp};
+use side_data;
+
+bitflags! {
+    pub struct PacketFlags: i32 {
+        const KEY = ff::AV_PKT_FLAG_KEY;
+        const CORRUPT = ff::AV_PKT_FLAG_CORRUPT;
+        const DISCARD = ff::AV_PKT_FLAG_DISCARD;
+        const TRUSTED = ff::AV_PKT_FLAG_TRUSTED;
+    }
+}
+
+/// A packet of compressed data - FFmpeg 3.4 packets do not record their time base, so we keep it
+/// alongside, starting out as 0/1 (unknown) until whatever made the packet sets it - until then
+/// the timestamps read as None
+pub struct Packet(*mut ff::AVPacket, Rational);
 
 impl Packet {
     pub fn alloc() -> Self {
         unsafe {
             match ff::av_packet_alloc() {
-                packet if !packet.is_null() => Packet(packet),
+                packet if !packet.is_null() => Packet(packet, Rational::new(0, 1)),
                 _ => panic!("out of memory"),
             }
         }
     }
 
-    pub fn dts(&self) -> i64 {
+    pub fn content_light_level(&self) -> Option<ContentLightLevel> {
+        self.side_data(ff::AVPacketSideDataType::AV_PKT_DATA_CONTENT_LIGHT_LEVEL).and_then(ContentLightLevel::from_bytes)
+    }
+
+    /// Makes a packet which owns a copy of the given data
+    pub fn copy_from_slice(data: &[u8]) -> Self {
+        let packet = Self::alloc();
+        unsafe {
+            // The padding FFmpeg reads past the end of the data is zeroed for us
+            match ff::av_new_packet(packet.0, data.len() as i32) {
+                e if 0 > e => panic!("out of memory"),
+                _ => copy_nonoverlapping(data.as_ptr(), (*packet.0).data, data.len()),
+            }
+        }
+
+        packet
+    }
+
+    /// Makes a packet which takes over the given data, such as a frame of an elementary stream
+    /// received over our own transport - the data is only copied if the Vec lacks the spare
+    /// capacity for AV_INPUT_BUFFER_PADDING_SIZE bytes of padding
+    pub fn from_bytes(mut data: Vec<u8>) -> Self {
+        // Decoders may read a little past the end of the data, so it must be followed by padding
+        let size = data.len();
+        let padding = ff::AV_INPUT_BUFFER_PADDING_SIZE as usize;
+        if data.capacity() - size < padding {
+            data.reserve_exact(padding);
+        }
+        data.resize(size + padding, 0);
+
+        let packet = Self::alloc();
+        unsafe {
+            let ptr = data.as_mut_ptr();
+            let len = data.len();
+            let opaque = Box::into_raw(Box::new(data));
+            let buf = ff::av_buffer_create(ptr, len as i32, Some(free_vec), opaque as *mut c_void, 0);
+            if buf.is_null() {
+                drop(Box::from_raw(opaque));
+                panic!("out of memory");
+            }
+
+            (*packet.0).buf = buf;
+            (*packet.0).data = ptr;
+            (*packet.0).size = size as i32;
+        }
+
+        packet
+    }
+
+    /// Returns the payload of the packet
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            match (*self.0).data {
+                data if data.is_null() => &[],
+                data => from_raw_parts(data, (*self.0).size as usize),
+            }
+        }
+    }
+
+    /// Makes a packet with its own copy of the data, rather than another reference to the same
+    /// buffer as clone() does
+    pub fn deep_copy(&self) -> Self {
+        let mut packet = self.clone();
+        packet.make_writable();
+
+        packet
+    }
+
+    /// Returns the rotation in degrees counterclockwise the packet should be displayed at
+    pub fn display_rotation(&self) -> Option<f64> {
+        self.side_data(ff::AVPacketSideDataType::AV_PKT_DATA_DISPLAYMATRIX).and_then(side_data::display_rotation)
+    }
+
+    pub fn dts(&self) -> Option<Timestamp> {
+        unsafe {
+            self.timestamp((*self.0).dts)
+        }
+    }
+
+    /// Returns the duration, or None if it is unknown
+    pub fn duration(&self) -> Option<Timestamp> {
+        unsafe {
+            match (*self.0).duration {
+                0 => None,
+                duration => self.timestamp(duration),
+            }
+        }
+    }
+
+    pub fn flags(&self) -> PacketFlags {
         unsafe {
-            (*self.0).dts
+            PacketFlags::from_bits_truncate((*self.0).flags)
         }
     }
 
-    pub fn duration(&self) -> i64 {
+    /// True if the packet holds a keyframe, which decoding can start from
+    pub fn is_key(&self) -> bool {
+        self.flags().contains(PacketFlags::KEY)
+    }
+
+    /// Copies the data into a buffer only this packet references, unless it already has one, so
+    /// it can be changed without affecting any clones
+    pub fn make_writable(&mut self) {
         unsafe {
-            (*self.0).duration
+            let packet = &mut *self.0;
+            if !packet.buf.is_null() && 0 != ff::av_buffer_is_writable(packet.buf) {
+                return;
+            }
+
+            let size = packet.size as usize;
+            let buf = ff::av_buffer_allocz((size + ff::AV_INPUT_BUFFER_PADDING_SIZE as usize) as i32);
+            if buf.is_null() {
+                panic!("out of memory");
+            }
+
+            if !packet.data.is_null() {
+                copy_nonoverlapping(packet.data, (*buf).data, size);
+            }
+
+            ff::av_buffer_unref(&mut packet.buf);
+            packet.buf = buf;
+            packet.data = (*buf).data;
         }
     }
 
+    pub fn mastering_display(&self) -> Option<MasteringDisplay> {
+        self.side_data(ff::AVPacketSideDataType::AV_PKT_DATA_MASTERING_DISPLAY_METADATA).and_then(MasteringDisplay::from_bytes)
+    }
+
     pub fn pos(&self) -> i64 {
         unsafe {
             (*self.0).pos
         }
     }
 
-    pub fn pts(&self) -> i64 {
+    pub fn pts(&self) -> Option<Timestamp> {
         unsafe {
-            (*self.0).pts
+            self.timestamp((*self.0).pts)
         }
     }
 
-    pub fn set_dts(&mut self, value: i64) {
+    /// Converts the pts, dts and duration from one time base to another, and takes on the new
+    /// time base - when remuxing, from is the input stream time base and to the output's
+    pub fn rescale_ts(&mut self, from: Rational, to: Rational) {
         unsafe {
-            (*self.0).dts = value;
+            ff::av_packet_rescale_ts(self.0, from.into(), to.into());
         }
+
+        self.1 = to;
     }
 
-    pub fn set_duration(&mut self, value: i64) {
+    pub fn set_content_light_level(&mut self, value: &ContentLightLevel) {
+        self.set_side_data(ff::AVPacketSideDataType::AV_PKT_DATA_CONTENT_LIGHT_LEVEL, &value.to_bytes());
+    }
+
+    pub fn set_display_rotation(&mut self, value: f64) {
+        self.set_side_data(ff::AVPacketSideDataType::AV_PKT_DATA_DISPLAYMATRIX, &side_data::display_matrix(value));
+    }
+
+    /// Sets the dts, rescaled to the time base of the packet
+    pub fn set_dts(&mut self, value: Option<Timestamp>) {
+        let value = self.conform(value);
+        unsafe {
+            (*self.0).dts = Timestamp::into_raw(value);
+        }
+    }
+
+    /// Sets the duration, rescaled to the time base of the packet
+    pub fn set_duration(&mut self, value: Option<Timestamp>) {
+        let value = self.conform(value);
+        unsafe {
+            (*self.0).duration = value.map_or(0, |value| value.value);
+        }
+    }
+
+    pub fn set_flags(&mut self, value: PacketFlags) {
         unsafe {
-            (*self.0).duration = value;
+            (*self.0).flags = value.bits();
         }
     }
 
+    pub fn set_key(&mut self, value: bool) {
+        let mut flags = self.flags();
+        flags.set(PacketFlags::KEY, value);
+        self.set_flags(flags);
+    }
+
+    pub fn set_mastering_display(&mut self, value: &MasteringDisplay) {
+        self.set_side_data(ff::AVPacketSideDataType::AV_PKT_DATA_MASTERING_DISPLAY_METADATA, &value.to_bytes());
+    }
+
     pub fn set_pos(&mut self, value: i64) {
         unsafe {
             (*self.0).pos = value;
         }
     }
 
-    pub fn set_pts(&mut self, value: i64) {
+    /// Sets the pts, rescaled to the time base of the packet
+    pub fn set_pts(&mut self, value: Option<Timestamp>) {
+        let value = self.conform(value);
         unsafe {
-            (*self.0).pts = value;
+            (*self.0).pts = Timestamp::into_raw(value);
+        }
+    }
+
+    /// Adds side data of the given type, replacing any the packet already has
+    pub fn set_side_data(&mut self, ty: ff::AVPacketSideDataType, data: &[u8]) {
+        unsafe {
+            match ff::av_packet_new_side_data(self.0, ty, data.len() as i32) {
+                ptr if ptr.is_null() => panic!("out of memory"),
+                ptr => copy_nonoverlapping(data.as_ptr(), ptr, data.len()),
+            }
         }
     }
 
@@ -68,11 +252,82 @@ impl Packet {
         }
     }
 
+    /// Sets the time base the timestamps are counted in, without changing their values
+    pub fn set_time_base(&mut self, value: Rational) {
+        self.1 = value;
+    }
+
+    /// Returns the side data of the given type, if the packet has any
+    pub fn side_data(&self, ty: ff::AVPacketSideDataType) -> Option<&[u8]> {
+        let mut size = 0;
+        unsafe {
+            match ff::av_packet_get_side_data(self.0, ty, &mut size) {
+                data if data.is_null() => None,
+                data => Some(side_data::bytes(data, size)),
+            }
+        }
+    }
+
+    pub fn side_data_iter(&self) -> PacketSideDataIter {
+        unsafe {
+            PacketSideDataIter::new((*self.0).side_data, (*self.0).side_data_elems as usize)
+        }
+    }
+
     pub fn stream_index(&self) -> usize {
         unsafe {
             (*self.0).stream_index as usize
         }
     }
+
+    /// Moves the data and properties into a new packet, leaving this one empty
+    pub fn take(&mut self) -> Self {
+        let mut packet = Self::alloc();
+        packet.1 = self.1;
+        unsafe {
+            ff::av_packet_move_ref(packet.0, self.0);
+        }
+
+        packet
+    }
+
+    pub fn time_base(&self) -> Rational {
+        self.1
+    }
+
+    /// Rescales a timestamp being set to the time base of the packet - a packet without a time
+    /// base takes on that of the timestamp
+    fn conform(&mut self, value: Option<Timestamp>) -> Option<Timestamp> {
+        let value = value?;
+        if 0 == self.1.num {
+            self.1 = value.time_base;
+        }
+
+        Some(value.rescale(self.1))
+    }
+
+    /// Reads a raw timestamp of the packet - None while the time base is unknown, as the value
+    /// means nothing without it
+    fn timestamp(&self, value: i64) -> Option<Timestamp> {
+        match self.1.num {
+            0 => None,
+            _ => Timestamp::from_raw(value, self.1),
+        }
+    }
+}
+
+impl Clone for Packet {
+    /// Makes another reference to the same data - data which is not reference counted is copied
+    fn clone(&self) -> Self {
+        let mut packet = Self::alloc();
+        packet.1 = self.1;
+        unsafe {
+            match ff::av_packet_ref(packet.0, self.0) {
+                e if 0 > e => panic!("out of memory"),
+                _ => packet,
+            }
+        }
+    }
 }
 
 impl Deref for Packet {
@@ -100,4 +355,11 @@ impl Drop for Packet {
             ff::av_packet_free(&mut self.0);
         }
     }
-}
\ No newline at end of file
+}
+
+// The buffer is reference counted atomically, so a packet may be dropped or cloned on any thread
+unsafe impl Send for Packet {}
+
+unsafe extern "C" fn free_vec(opaque: *mut c_void, _data: *mut u8) {
+    drop(Box::from_raw(opaque as *mut Vec<u8>));
+}
@@ -0,0 +1,178 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::i32;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[cfg(feature = "bigrational")]
+use num::{BigInt, BigRational, ToPrimitive};
+
+use ::ff;
+
+/// A fraction such as a time base or frame rate, in the same 32 bit form FFmpeg uses - compares
+/// by value, so 1/2 equals 2/4
+#[derive(Clone, Copy, Debug)]
+pub struct Rational {
+    pub num: i32,
+    pub den: i32,
+}
+
+impl Rational {
+    pub fn new(num: i32, den: i32) -> Self {
+        Rational {
+            num,
+            den,
+        }
+    }
+
+    /// Returns the closest fraction to a floating point value - infinity becomes 1/0 and NaN 0/0
+    pub fn approximate(value: f64) -> Self {
+        unsafe {
+            ff::av_d2q(value, i32::MAX).into()
+        }
+    }
+
+    /// Reduces a ratio of 64 bit values, such as 90000/3003, to the closest fraction which fits
+    pub fn from_ratio(num: i64, den: i64) -> Self {
+        let mut result = Rational::new(0, 1);
+        unsafe {
+            ff::av_reduce(&mut result.num, &mut result.den, num, den, i32::MAX as i64);
+        }
+
+        result
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Swaps the numerator and denominator, turning a frame rate into a frame duration
+    pub fn invert(&self) -> Self {
+        Rational::new(self.den, self.num)
+    }
+
+    /// Returns the fraction in its lowest terms
+    pub fn reduce(&self) -> Self {
+        Self::from_ratio(self.num as i64, self.den as i64)
+    }
+
+    /// Converts a timestamp counted in this time base to one counted in another, rounding to the
+    /// nearest value
+    pub fn rescale(&self, value: i64, to: Rational) -> i64 {
+        unsafe {
+            ff::av_rescale_q(value, (*self).into(), to.into())
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+
+    fn add(self, other: Rational) -> Self::Output {
+        unsafe {
+            ff::av_add_q(self.into(), other.into()).into()
+        }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}/{}", self.num, self.den)
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+
+    fn div(self, other: Rational) -> Self::Output {
+        unsafe {
+            ff::av_div_q(self.into(), other.into()).into()
+        }
+    }
+}
+
+impl From<ff::AVRational> for Rational {
+    fn from(value: ff::AVRational) -> Self {
+        Rational::new(value.num, value.den)
+    }
+}
+
+impl From<Rational> for ff::AVRational {
+    fn from(value: Rational) -> Self {
+        ff::AVRational {
+            num: value.num,
+            den: value.den,
+        }
+    }
+}
+
+impl From<(i32, i32)> for Rational {
+    fn from((num, den): (i32, i32)) -> Self {
+        Rational::new(num, den)
+    }
+}
+
+#[cfg(feature = "bigrational")]
+impl<'a> From<&'a BigRational> for Rational {
+    /// Takes the closest fraction which fits, rather than failing for values which do not
+    fn from(value: &'a BigRational) -> Self {
+        match (value.numer().to_i64(), value.denom().to_i64()) {
+            (Some(num), Some(den)) => Self::from_ratio(num, den),
+            _ => match (value.numer().to_f64(), value.denom().to_f64()) {
+                (Some(num), Some(den)) => Self::approximate(num / den),
+                _ => Rational::new(0, 0),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "bigrational")]
+impl From<Rational> for BigRational {
+    fn from(value: Rational) -> Self {
+        BigRational::new(BigInt::from(value.num), BigInt::from(value.den))
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+
+    fn mul(self, other: Rational) -> Self::Output {
+        unsafe {
+            ff::av_mul_q(self.into(), other.into()).into()
+        }
+    }
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Rational) -> bool {
+        Some(Ordering::Equal) == self.partial_cmp(other)
+    }
+}
+
+impl PartialOrd for Rational {
+    /// Compares the same way as av_cmp_q - None when either side is 0/0
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        let diff = self.num as i64 * other.den as i64 - other.num as i64 * self.den as i64;
+        if 0 != diff {
+            // Negative denominators flip the sign of the cross product
+            let negative = (0 > diff) ^ (0 > self.den) ^ (0 > other.den);
+            Some(if negative { Ordering::Less } else { Ordering::Greater })
+        } else if 0 != self.den && 0 != other.den {
+            Some(Ordering::Equal)
+        } else if 0 != self.num && 0 != other.num {
+            // Both are infinite
+            Some((0 > other.num).cmp(&(0 > self.num)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+
+    fn sub(self, other: Rational) -> Self::Output {
+        unsafe {
+            ff::av_sub_q(self.into(), other.into()).into()
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ptr::read_unaligned;
+use std::slice::from_raw_parts;
+
+use ::{ff, Rational};
+
+/// Returns the rotation in degrees counterclockwise of a display matrix, as found in
+/// AV_PKT_DATA_DISPLAYMATRIX or AV_FRAME_DATA_DISPLAYMATRIX side data - None if the data is not
+/// a valid matrix
+pub fn display_rotation(data: &[u8]) -> Option<f64> {
+    if data.len() < 9 * size_of::<i32>() {
+        return None;
+    }
+
+    let matrix: [i32; 9] = unsafe {
+        read_unaligned(data.as_ptr() as *const [i32; 9])
+    };
+    let rotation = unsafe {
+        ff::av_display_rotation_get(matrix.as_ptr())
+    };
+
+    if rotation.is_nan() {
+        None
+    } else {
+        Some(rotation)
+    }
+}
+
+/// Makes the display matrix for a rotation in degrees counterclockwise, as side data bytes
+pub fn display_matrix(rotation: f64) -> Vec<u8> {
+    let mut matrix = [0i32; 9];
+    unsafe {
+        ff::av_display_rotation_set(matrix.as_mut_ptr(), rotation);
+    }
+
+    struct_bytes(&matrix)
+}
+
+/// Light levels of HDR content, from AV_PKT_DATA_CONTENT_LIGHT_LEVEL or
+/// AV_FRAME_DATA_CONTENT_LIGHT_LEVEL side data - both in candelas per square metre
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ContentLightLevel {
+    /// The brightest pixel of the whole stream (MaxCLL)
+    pub max_content: u32,
+    /// The brightest frame average of the whole stream (MaxFALL)
+    pub max_frame_average: u32,
+}
+
+impl ContentLightLevel {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        read_struct::<ff::AVContentLightMetadata>(data).map(|metadata| ContentLightLevel {
+            max_content: metadata.MaxCLL as u32,
+            max_frame_average: metadata.MaxFALL as u32,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        struct_bytes(&ff::AVContentLightMetadata {
+            MaxCLL: self.max_content as _,
+            MaxFALL: self.max_frame_average as _,
+        })
+    }
+}
+
+/// The colour volume of the display HDR content was mastered on, from
+/// AV_PKT_DATA_MASTERING_DISPLAY_METADATA or AV_FRAME_DATA_MASTERING_DISPLAY_METADATA side data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MasteringDisplay {
+    /// CIE 1931 xy chromaticity of the red, green and blue primaries
+    pub primaries: Option<[(Rational, Rational); 3]>,
+    /// CIE 1931 xy chromaticity of the white point
+    pub white_point: Option<(Rational, Rational)>,
+    /// Minimum and maximum luminance in candelas per square metre
+    pub luminance: Option<(Rational, Rational)>,
+}
+
+impl MasteringDisplay {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        read_struct::<ff::AVMasteringDisplayMetadata>(data).map(|metadata| {
+            let xy = |xy: [ff::AVRational; 2]| (Rational::from(xy[0]), Rational::from(xy[1]));
+            let (primaries, white_point) = match metadata.has_primaries {
+                0 => (None, None),
+                _ => {
+                    let primaries = metadata.display_primaries;
+                    (Some([xy(primaries[0]), xy(primaries[1]), xy(primaries[2])]), Some(xy(metadata.white_point)))
+                }
+            };
+
+            MasteringDisplay {
+                primaries,
+                white_point,
+                luminance: match metadata.has_luminance {
+                    0 => None,
+                    _ => Some((metadata.min_luminance.into(), metadata.max_luminance.into())),
+                },
+            }
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let zero = Rational::new(0, 1);
+        let xy = |xy: (Rational, Rational)| [ff::AVRational::from(xy.0), ff::AVRational::from(xy.1)];
+        let primaries = self.primaries.unwrap_or([(zero, zero); 3]);
+        let white_point = self.white_point.unwrap_or((zero, zero));
+        let luminance = self.luminance.unwrap_or((zero, zero));
+
+        struct_bytes(&ff::AVMasteringDisplayMetadata {
+            display_primaries: [xy(primaries[0]), xy(primaries[1]), xy(primaries[2])],
+            white_point: xy(white_point),
+            min_luminance: luminance.0.into(),
+            max_luminance: luminance.1.into(),
+            has_primaries: (self.primaries.is_some() && self.white_point.is_some()) as i32,
+            has_luminance: self.luminance.is_some() as i32,
+        })
+    }
+}
+
+/// Iterates over the side data of a packet, giving the type and bytes of each
+pub struct PacketSideDataIter<'a> {
+    ptr: *const ff::AVPacketSideData,
+    remaining: usize,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> PacketSideDataIter<'a> {
+    pub(crate) fn new(ptr: *const ff::AVPacketSideData, len: usize) -> Self {
+        PacketSideDataIter {
+            ptr,
+            remaining: if ptr.is_null() { 0 } else { len },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for PacketSideDataIter<'a> {
+    type Item = (ff::AVPacketSideDataType, &'a [u8]);
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if 0 == self.remaining {
+            return None;
+        }
+
+        unsafe {
+            let side_data = &*self.ptr;
+            self.ptr = self.ptr.offset(1);
+            self.remaining -= 1;
+
+            Some((side_data.type_, bytes(side_data.data, side_data.size)))
+        }
+    }
+}
+
+/// Iterates over the side data of a frame, giving the type and bytes of each
+pub struct FrameSideDataIter<'a> {
+    ptr: *const *mut ff::AVFrameSideData,
+    remaining: usize,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> FrameSideDataIter<'a> {
+    pub(crate) fn new(ptr: *const *mut ff::AVFrameSideData, len: usize) -> Self {
+        FrameSideDataIter {
+            ptr,
+            remaining: if ptr.is_null() { 0 } else { len },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for FrameSideDataIter<'a> {
+    type Item = (ff::AVFrameSideDataType, &'a [u8]);
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if 0 == self.remaining {
+            return None;
+        }
+
+        unsafe {
+            let side_data = &**self.ptr;
+            self.ptr = self.ptr.offset(1);
+            self.remaining -= 1;
+
+            Some((side_data.type_, bytes(side_data.data, side_data.size)))
+        }
+    }
+}
+
+/// Borrows side data bytes, which FFmpeg may leave null when empty
+pub unsafe fn bytes<'a>(data: *const u8, size: i32) -> &'a [u8] {
+    if data.is_null() || 0 >= size {
+        &[]
+    } else {
+        from_raw_parts(data, size as usize)
+    }
+}
+
+fn read_struct<T: Copy>(data: &[u8]) -> Option<T> {
+    if data.len() < size_of::<T>() {
+        None
+    } else {
+        unsafe {
+            Some(read_unaligned(data.as_ptr() as *const T))
+        }
+    }
+}
+
+fn struct_bytes<T>(value: &T) -> Vec<u8> {
+    unsafe {
+        from_raw_parts(value as *const T as *const u8, size_of::<T>()).to_vec()
+    }
+}
@@ -1,14 +1,15 @@
-use num::BigRational;
+use std::marker::PhantomData;
 
-use ::{avrational_from_bigrational, bigrational_from_avrational, CodecParams, ff, FFmpegError};
+use ::{CodecParams, ff, FFmpegError, Rational, Timestamp};
 
+/// A stream borrowed from an InputContext or OutputContext
 #[derive(Debug, PartialEq)]
-pub struct Stream(*mut ff::AVStream);
+pub struct Stream<'a>(*mut ff::AVStream, PhantomData<&'a ()>);
 
-impl Stream {
-    pub fn avg_frame_rate(&self) -> Result<BigRational, FFmpegError> {
+impl<'a> Stream<'a> {
+    pub fn avg_frame_rate(&self) -> Rational {
         unsafe {
-            bigrational_from_avrational(&(*self.0).avg_frame_rate)
+            (*self.0).avg_frame_rate.into()
         }
     }
 
@@ -27,6 +28,13 @@ impl Stream {
         }
     }
 
+    /// Returns the duration of the stream, if the container records it
+    pub fn duration(&self) -> Option<Timestamp> {
+        unsafe {
+            Timestamp::from_raw((*self.0).duration, self.time_base())
+        }
+    }
+
     pub fn index(&self) -> usize {
         unsafe {
             (*self.0).index as usize
@@ -39,21 +47,28 @@ impl Stream {
         }
     }
 
-    pub fn set_time_base(&mut self, value: &BigRational) -> Result<(), FFmpegError> {
-        Ok(unsafe {
-            (*self.0).time_base = avrational_from_bigrational(value)?;
-        })
+    pub fn set_time_base(&mut self, value: Rational) {
+        unsafe {
+            (*self.0).time_base = value.into();
+        }
+    }
+
+    /// Returns the presentation time of the first frame, if it is known
+    pub fn start_time(&self) -> Option<Timestamp> {
+        unsafe {
+            Timestamp::from_raw((*self.0).start_time, self.time_base())
+        }
     }
 
-    pub fn time_base(&self) -> Result<BigRational, FFmpegError> {
+    pub fn time_base(&self) -> Rational {
         unsafe {
-            bigrational_from_avrational(&(*self.0).time_base)
+            (*self.0).time_base.into()
         }
     }
 }
 
-impl From<*mut ff::AVStream> for Stream {
+impl<'a> From<*mut ff::AVStream> for Stream<'a> {
     fn from(ptr: *mut ff::AVStream) -> Self {
-        Stream(ptr)
+        Stream(ptr, PhantomData)
     }
 }
\ No newline at end of file
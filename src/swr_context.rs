@@ -0,0 +1,162 @@
+use std::ptr::null_mut;
+
+use libc::c_void;
+
+use ::{ff, FFmpegError, Frame, Options, Rational, Timestamp};
+
+/// Converts audio between sample formats, sample rates and channel layouts - the returned frames
+/// are timed in 1/dst_sample_rate
+pub struct SwrContext {
+    ptr: *mut ff::SwrContext,
+    dst_channel_layout: u64,
+    dst_format: ff::AVSampleFormat,
+    dst_sample_rate: usize,
+    next_pts: Option<i64>,
+    src_channel_layout: u64,
+    src_format: ff::AVSampleFormat,
+    src_sample_rate: usize,
+}
+
+impl SwrContext {
+    pub fn new(src_channel_layout: u64, src_format: ff::AVSampleFormat, src_sample_rate: usize, dst_channel_layout: u64, dst_format: ff::AVSampleFormat, dst_sample_rate: usize) -> Result<Self, FFmpegError> {
+        unsafe {
+            let context = ff::swr_alloc_set_opts(null_mut(),
+                                                 dst_channel_layout as i64,
+                                                 dst_format,
+                                                 dst_sample_rate as i32,
+                                                 src_channel_layout as i64,
+                                                 src_format,
+                                                 src_sample_rate as i32,
+                                                 0,
+                                                 null_mut());
+            if context.is_null() {
+                panic!("out of memory");
+            }
+
+            // From here on the context is freed when dropped
+            let swr_context = SwrContext {
+                ptr: context,
+                dst_channel_layout,
+                dst_format,
+                dst_sample_rate,
+                next_pts: None,
+                src_channel_layout,
+                src_format,
+                src_sample_rate,
+            };
+
+            match ff::swr_init(context) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                _ => Ok(swr_context),
+            }
+        }
+    }
+
+    /// Converts the samples of a frame - some of them may be buffered and returned by
+    /// a later call, or by flush() at the end of the stream. The first frame converted sets the
+    /// starting timestamp and the returned frames continue on from it
+    pub fn convert(&mut self, src: &Frame) -> Result<Frame, FFmpegError> {
+        // swr_convert trusts the frame to hold the layout and format the context was made for
+        let src_channels = unsafe {
+            ff::av_get_channel_layout_nb_channels(self.src_channel_layout) as usize
+        };
+        if src.sample_format() != self.src_format
+            || src.sample_rate() != self.src_sample_rate
+            || src.channels() != src_channels
+            || (0 != src.channel_layout() && src.channel_layout() != self.src_channel_layout) {
+            return Err(FFmpegError::InvalidArgument);
+        }
+
+        if self.next_pts.is_none() {
+            self.next_pts = Some(src.pts().map_or(0, |pts| pts.rescale(self.time_base()).value));
+        }
+
+        unsafe {
+            let samples = match ff::swr_get_out_samples(self.ptr, src.samples() as i32) {
+                e if 0 > e => return Err(FFmpegError::from(e)),
+                samples => samples,
+            };
+
+            let mut dst = self.alloc_frame(samples as usize)?;
+            match ff::swr_convert(self.ptr, dst.extended_data, samples, src.extended_data as *mut *const u8, src.samples() as i32) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                converted => {
+                    dst.set_samples(converted as usize);
+                    self.set_timing(&mut dst);
+                    Ok(dst)
+                }
+            }
+        }
+    }
+
+    /// Returns the number of output samples still buffered inside the context
+    pub fn delay(&self) -> usize {
+        unsafe {
+            ff::swr_get_delay(self.ptr, self.dst_sample_rate as i64) as usize
+        }
+    }
+
+    /// Returns the buffered tail of the stream, if there is any
+    pub fn flush(&mut self) -> Result<Option<Frame>, FFmpegError> {
+        unsafe {
+            let samples = match ff::swr_get_out_samples(self.ptr, 0) {
+                e if 0 > e => return Err(FFmpegError::from(e)),
+                0 => return Ok(None),
+                samples => samples,
+            };
+
+            let mut dst = self.alloc_frame(samples as usize)?;
+            match ff::swr_convert(self.ptr, dst.extended_data, samples, null_mut(), 0) {
+                e if 0 > e => Err(FFmpegError::from(e)),
+                0 => Ok(None),
+                converted => {
+                    dst.set_samples(converted as usize);
+                    self.set_timing(&mut dst);
+                    Ok(Some(dst))
+                }
+            }
+        }
+    }
+
+    fn alloc_frame(&self, samples: usize) -> Result<Frame, FFmpegError> {
+        let mut frame = Frame::alloc();
+        frame.set_channel_layout(self.dst_channel_layout);
+        frame.set_channels(unsafe {
+            ff::av_get_channel_layout_nb_channels(self.dst_channel_layout) as usize
+        });
+        frame.set_sample_format(self.dst_format);
+        frame.set_sample_rate(self.dst_sample_rate);
+        frame.set_samples(samples);
+        frame.get_buffer()?;
+
+        Ok(frame)
+    }
+
+    fn set_timing(&mut self, frame: &mut Frame) {
+        let pts = self.next_pts.unwrap_or(0);
+        frame.set_time_base(self.time_base());
+        frame.set_pts(Some(Timestamp::new(pts, self.time_base())));
+        self.next_pts = Some(pts + frame.samples() as i64);
+    }
+
+    pub fn time_base(&self) -> Rational {
+        Rational::new(1, self.dst_sample_rate as i32)
+    }
+}
+
+unsafe impl Options for SwrContext {
+    fn options_ptr(&self) -> *mut c_void {
+        self.ptr as *mut c_void
+    }
+}
+
+impl Drop for SwrContext {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ff::swr_free(&mut self.ptr);
+        }
+    }
+}
+
+unsafe impl Send for SwrContext {}
@@ -1,7 +1,9 @@
 use std::ptr::{null, null_mut};
 //use std::slice::from_raw_parts;
 
-use ::{ff, FFmpegError, Frame};
+use libc::c_void;
+
+use ::{ff, FFmpegError, Frame, Options};
 
 pub struct SwsContext(*mut ff::SwsContext);
 
@@ -112,6 +114,12 @@ impl SwsContext {
     }
 }
 
+unsafe impl Options for SwsContext {
+    fn options_ptr(&self) -> *mut c_void {
+        self.0 as *mut c_void
+    }
+}
+
 impl Drop for SwsContext {
     #[inline]
     fn drop(&mut self) {
@@ -119,4 +127,6 @@ impl Drop for SwsContext {
             ff::sws_freeContext(self.0);
         }
     }
-}
\ No newline at end of file
+}
+
+unsafe impl Send for SwsContext {}
@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::Duration;
+
+use ::{ff, Packet, Rational};
+
+/// How rescaling rounds values which fall between two ticks of the new time base
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rounding {
+    /// Towards negative infinity
+    Down,
+    /// Away from zero
+    Inf,
+    /// To the nearest tick, with halfway values rounded away from zero
+    NearInf,
+    /// Towards positive infinity
+    Up,
+    /// Towards zero
+    Zero,
+}
+
+impl From<Rounding> for ff::AVRounding {
+    fn from(rounding: Rounding) -> Self {
+        match rounding {
+            Rounding::Down => ff::AVRounding::AV_ROUND_DOWN,
+            Rounding::Inf => ff::AVRounding::AV_ROUND_INF,
+            Rounding::NearInf => ff::AVRounding::AV_ROUND_NEAR_INF,
+            Rounding::Up => ff::AVRounding::AV_ROUND_UP,
+            Rounding::Zero => ff::AVRounding::AV_ROUND_ZERO,
+        }
+    }
+}
+
+/// A point in time counted in ticks of a time base - where FFmpeg uses AV_NOPTS_VALUE for an
+/// unknown timestamp we use None, so a Timestamp always holds a real value
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp {
+    pub value: i64,
+    pub time_base: Rational,
+}
+
+impl Timestamp {
+    pub fn new(value: i64, time_base: Rational) -> Self {
+        Timestamp {
+            value,
+            time_base,
+        }
+    }
+
+    /// Makes a timestamp from a raw FFmpeg value, which is None if it is AV_NOPTS_VALUE
+    pub fn from_raw(value: i64, time_base: Rational) -> Option<Self> {
+        match value {
+            ff::AV_NOPTS_VALUE => None,
+            value => Some(Timestamp::new(value, time_base)),
+        }
+    }
+
+    /// Converts a duration since zero to the nearest tick of the given time base
+    pub fn from_duration(duration: Duration, time_base: Rational) -> Self {
+        let nanos = duration.as_secs() as i64 * 1_000_000_000 + duration.subsec_nanos() as i64;
+        Timestamp::new(nanos, Rational::new(1, 1_000_000_000)).rescale(time_base)
+    }
+
+    /// Converts seconds to the nearest tick of the given time base
+    pub fn from_seconds(seconds: f64, time_base: Rational) -> Self {
+        Timestamp::new((seconds / time_base.as_f64()).round() as i64, time_base)
+    }
+
+    /// Returns the raw value FFmpeg expects, using AV_NOPTS_VALUE for None
+    pub fn into_raw(timestamp: Option<Timestamp>) -> i64 {
+        timestamp.map_or(ff::AV_NOPTS_VALUE, |timestamp| timestamp.value)
+    }
+
+    /// Returns the time since zero - None for timestamps before zero
+    pub fn as_duration(&self) -> Option<Duration> {
+        match self.rescale_rnd(Rational::new(1, 1_000_000_000), Rounding::Down).value {
+            nanos if 0 > nanos => None,
+            nanos => Some(Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32)),
+        }
+    }
+
+    pub fn as_seconds(&self) -> f64 {
+        self.value as f64 * self.time_base.as_f64()
+    }
+
+    /// Converts to the nearest tick of another time base
+    pub fn rescale(&self, time_base: Rational) -> Self {
+        self.rescale_rnd(time_base, Rounding::NearInf)
+    }
+
+    /// Converts to another time base, rounding as asked
+    pub fn rescale_rnd(&self, time_base: Rational, rounding: Rounding) -> Self {
+        let value = unsafe {
+            ff::av_rescale_q_rnd(self.value, self.time_base.into(), time_base.into(), rounding.into())
+        };
+
+        Timestamp::new(value, time_base)
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:.6}s", self.as_seconds())
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl Ord for Timestamp {
+    /// Compares the times the timestamps stand for, even when their time bases differ
+    fn cmp(&self, other: &Timestamp) -> Ordering {
+        unsafe {
+            ff::av_compare_ts(self.value, self.time_base.into(), other.value, other.time_base.into()).cmp(&0)
+        }
+    }
+}
+
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Timestamp) -> bool {
+        Ordering::Equal == self.cmp(other)
+    }
+}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Timestamp) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Remaps the timestamps of one stream's packets for stream copy remuxing - converts them from the
+/// input stream time base to the output's, shifts them so the output starts at zero, and nudges
+/// any dts which does not increase so the muxer accepts it
+pub struct TimestampRemapper {
+    from: Rational,
+    to: Rational,
+    offset: Option<i64>,
+    last_dts: Option<i64>,
+}
+
+impl TimestampRemapper {
+    /// Shifts by the given start time - give every stream the same start, such as
+    /// InputContext::start_time(), to keep them in sync with each other
+    pub fn new(from: Rational, to: Rational, start: Timestamp) -> Self {
+        TimestampRemapper {
+            from,
+            to,
+            offset: Some(start.rescale(from).value),
+            last_dts: None,
+        }
+    }
+
+    /// Shifts by the first timestamp of the stream instead - streams which start at different
+    /// times fall out of sync with each other, so this only suits remuxing a single stream
+    pub fn with_own_start(from: Rational, to: Rational) -> Self {
+        TimestampRemapper {
+            from,
+            to,
+            offset: None,
+            last_dts: None,
+        }
+    }
+
+    /// Remaps a packet read from the input stream, leaving it in the output time base
+    pub fn remap(&mut self, packet: &mut Packet) {
+        packet.set_time_base(self.from);
+        if self.offset.is_none() {
+            self.offset = packet.dts().or_else(|| packet.pts()).map(|start| start.value);
+        }
+
+        let offset = self.offset.unwrap_or(0);
+        let pts = packet.pts().map(|pts| Timestamp::new(pts.value - offset, self.from).rescale(self.to));
+        let mut dts = packet.dts().map(|dts| Timestamp::new(dts.value - offset, self.from).rescale(self.to));
+        let duration = packet.duration();
+
+        if let (Some(last_dts), Some(dts)) = (self.last_dts, dts.as_mut()) {
+            if last_dts >= dts.value {
+                debug!("Moving non-monotonic dts {} forward to {}", dts.value, last_dts + 1);
+                dts.value = last_dts + 1;
+            }
+        }
+
+        // The pts can never come before the dts
+        let pts = match (pts, dts) {
+            (Some(pts), Some(dts)) if pts.value < dts.value => Some(dts),
+            (pts, _) => pts,
+        };
+
+        packet.set_time_base(self.to);
+        packet.set_pts(pts);
+        packet.set_dts(dts);
+        packet.set_duration(duration.map(|duration| duration.rescale(self.to)));
+        self.last_dts = dts.map(|dts| dts.value).or(self.last_dts);
+    }
+}